@@ -0,0 +1,90 @@
+//! Atomic replacement of files in XDG directories.
+
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::{Error, XdgDir, io_error, xdg_user_dir};
+
+/// Whether `a` and `b` (which must both exist) are on the same filesystem, so that one can be renamed to a location
+/// next to the other rather than copied.
+///
+/// On Unix this compares the device IDs. Elsewhere it only compares the path prefixes (e.g. the drive letter on Windows).
+pub fn same_filesystem(a: &Path, b: &Path) -> io::Result<bool> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        Ok(a.metadata()?.dev() == b.metadata()?.dev())
+    }
+
+    #[cfg(not(unix))]
+    {
+        let (a, b) = (a.canonicalize()?, b.canonicalize()?);
+        Ok(a.components().next() == b.components().next())
+    }
+}
+
+/// A tag for naming temporary files, unique between processes and between threads of this process using it concurrently.
+pub(crate) fn unique_tag() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    format!(
+        "{}-{}",
+        process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// Atomically replace the user-level file `suffix` for a given XDG basedir with `contents`, creating any missing parent
+/// directories. Readers see either the old or the new contents, never a partially written file. Returns the path.
+///
+/// The contents are written to a temporary file in the same directory, synced, and renamed over the target. The
+/// permissions of an existing file are kept.
+pub fn xdg_write_atomic(
+    xdg_dir: &XdgDir,
+    suffix: &str,
+    contents: impl AsRef<[u8]>,
+) -> Result<PathBuf, Error> {
+    let path = xdg_user_dir(xdg_dir, suffix)?;
+    let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+        return Err(Error::Io(path, io::ErrorKind::InvalidFilename));
+    };
+    fs::create_dir_all(parent).map_err(io_error(parent))?;
+
+    let mut tmp_name = name.to_os_string();
+    tmp_name.push(format!(".{}.tmp", unique_tag()));
+    let tmp = parent.join(tmp_name);
+
+    let result = File::create(&tmp)
+        .and_then(|mut file| {
+            // Keep the permissions of a file being replaced, rather than widening e.g. a 0600 file to the umask default
+            if let Ok(meta) = fs::metadata(&path)
+                && meta.is_file()
+            {
+                file.set_permissions(meta.permissions())?;
+            }
+            file.write_all(contents.as_ref())?;
+            file.sync_all()
+        })
+        .map_err(io_error(&tmp))
+        .and_then(|()| fs::rename(&tmp, &path).map_err(io_error(&path)));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+
+    result.map(|()| path)
+}
+
+/// Atomically write the user-level file `suffix` for a given XDG basedir (e.g. a state file under `dirs::STATE`), as with
+/// [`xdg_write_atomic`]. The temporary file is created next to the target so the rename stays on one filesystem, and is
+/// removed if writing fails. Returns the final path.
+pub fn xdg_write_user_file(
+    xdg_dir: &XdgDir,
+    suffix: &str,
+    contents: impl AsRef<[u8]>,
+) -> Result<PathBuf, Error> {
+    xdg_write_atomic(xdg_dir, suffix, contents)
+}
@@ -0,0 +1,140 @@
+//! Typed config loading, enabled by the `toml` and/or `json` features.
+
+use std::{fs, path::Path};
+
+use serde::de::DeserializeOwned;
+
+use crate::{Error, context::push_suffix, dirs, io_error, xdg_candidate_paths, xdg_location_of};
+
+/// Extensions of the config formats enabled by features, in the order they are tried.
+const EXTENSIONS: &[&str] = &[
+    #[cfg(feature = "toml")]
+    "toml",
+    #[cfg(feature = "json")]
+    "json",
+];
+
+/// Deserialize `contents` according to the format for `ext`, which must be one of [`EXTENSIONS`].
+fn parse<T: DeserializeOwned>(ext: &str, contents: &str) -> Result<T, String> {
+    match ext {
+        #[cfg(feature = "toml")]
+        "toml" => toml::from_str(contents).map_err(|e| e.to_string()),
+        #[cfg(feature = "json")]
+        "json" => serde_json::from_str(contents).map_err(|e| e.to_string()),
+        _ => unreachable!("unsupported config format {ext}"),
+    }
+}
+
+/// The file names to try for `suffix` along with their format, in order: `suffix` itself if it has the extension of an
+/// enabled format, otherwise `suffix` with each enabled extension appended.
+fn candidate_names(suffix: &str) -> Vec<(String, &'static str)> {
+    match Path::new(suffix)
+        .extension()
+        .and_then(|ext| EXTENSIONS.iter().find(|&&e| ext == e))
+    {
+        Some(ext) => vec![(suffix.to_string(), ext)],
+        None => EXTENSIONS
+            .iter()
+            .map(|ext| (format!("{suffix}.{ext}"), *ext))
+            .collect(),
+    }
+}
+
+/// Find and deserialize a config file, with the format determined by its extension.
+///
+/// If `suffix` has the extension of an enabled format (`.toml` with the `toml` feature, `.json` with the `json` feature)
+/// it is found with [`xdg_location_of`] and parsed as that format. Otherwise each enabled format is tried in turn by
+/// appending its extension to `suffix`, using the first file that exists and parses successfully.
+///
+/// Returns [`Error::Parse`] listing every file found and why it failed to parse if none parsed, or [`Error::NotFound`]
+/// if no file was found at all. If no file was found but a candidate could not be checked for lack of permission, this
+/// returns [`Error::PermissionDenied`] for the first such candidate instead.
+pub fn xdg_load_config<T: DeserializeOwned>(suffix: &str) -> Result<T, Error> {
+    let mut checked = vec![];
+    let mut failures = vec![];
+    let mut denied = None;
+
+    for (name, ext) in candidate_names(suffix) {
+        let path = match xdg_location_of(&dirs::CONFIG, &name) {
+            Ok(path) => path,
+            Err(Error::NotFound(_, paths)) => {
+                checked.extend(paths);
+                continue;
+            }
+            // Another format may still be found
+            Err(Error::PermissionDenied(path)) => {
+                denied.get_or_insert(path);
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+
+        let contents = fs::read_to_string(&path).map_err(io_error(&path))?;
+        match parse(ext, &contents) {
+            Ok(config) => return Ok(config),
+            Err(e) => failures.push((path, e)),
+        }
+    }
+
+    if !failures.is_empty() {
+        Err(Error::Parse(failures))
+    } else if let Some(path) = denied {
+        Err(Error::PermissionDenied(path))
+    } else {
+        Err(Error::NotFound(suffix.to_string(), checked))
+    }
+}
+
+/// Combining config layers loaded by [`xdg_load_layered`].
+pub trait Merge {
+    /// Overlay `higher`, which takes precedence over `self`, e.g. replacing each value of `self` that is set in `higher`.
+    fn merge(&mut self, higher: Self);
+}
+
+/// Find and deserialize every copy of a config file across the config search path, and merge them into one value.
+/// At most one file is used from each directory of the search path: if `suffix` has no extension, the first enabled
+/// format that exists there, in the same order as [`xdg_load_config`]. The files are merged from lowest to highest
+/// precedence: starting from the last system directory, each file is [merged](Merge::merge) into the result so far,
+/// ending with the user's file.
+///
+/// Returns [`Error::Parse`] listing every file that failed to parse if any did, since silently skipping a layer would
+/// give a different config than intended, or [`Error::NotFound`] if no file was found at all.
+pub fn xdg_load_layered<T: DeserializeOwned + Merge>(suffix: &str) -> Result<T, Error> {
+    let names = candidate_names(suffix);
+    let mut checked = vec![];
+    let mut layers = vec![];
+    let mut failures = vec![];
+
+    for dir in xdg_candidate_paths(&dirs::CONFIG, "") {
+        for (name, ext) in &names {
+            let mut path = dir.clone();
+            push_suffix(&mut path, name);
+            checked.push(path.to_string_lossy().to_string());
+            if !path.is_file() {
+                continue;
+            }
+
+            let contents = fs::read_to_string(&path).map_err(io_error(&path))?;
+            match parse(ext, &contents) {
+                Ok(layer) => layers.push(layer),
+                Err(e) => failures.push((path, e)),
+            }
+            // Only one file per directory is a layer, any other formats next to it are ignored
+            break;
+        }
+    }
+
+    if !failures.is_empty() {
+        return Err(Error::Parse(failures));
+    }
+
+    // Layers were found in precedence order, merge starting from the lowest
+    let mut layers = layers.into_iter().rev();
+    let mut merged: T = layers
+        .next()
+        .ok_or_else(|| Error::NotFound(suffix.to_string(), checked))?;
+    for layer in layers {
+        merged.merge(layer);
+    }
+    Ok(merged)
+}
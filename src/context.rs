@@ -0,0 +1,674 @@
+//! Configurable resolution of XDG paths. The free functions in the crate root use the default context.
+
+#[cfg(feature = "fs")]
+use std::{
+    cell::RefCell,
+    fs::{DirBuilder, File, Metadata},
+    io::{self, Write},
+    time::Duration,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::{OsStr, OsString},
+    fmt,
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError},
+    time::SystemTime,
+};
+
+#[cfg(feature = "fs")]
+use crate::io_error;
+use crate::{Env, Error, ProcessEnv, SysDirSource, XdgDir, debug, dirs};
+
+/// Options controlling how XDG paths are resolved.
+/// The default context behaves exactly like the free functions (e.g. [`crate::xdg_user_dir`]).
+#[derive(Clone, Default)]
+pub struct XdgContext {
+    no_home_fallback: bool,
+    etc_fallback: bool,
+    tilde_expansion: bool,
+    secure_mode: bool,
+    strict_empty_dirs: bool,
+    fs_root: Option<PathBuf>,
+    systemd_dirs: bool,
+    length_check: bool,
+    /// Where to read environment variables from, or the process environment if `None`
+    env: Option<Arc<dyn Env + Send + Sync>>,
+    /// Resolved user paths, if caching is enabled
+    cache: Option<Arc<Mutex<PathCache>>>,
+    /// Source of the current time, or the system clock if `None`
+    clock: Option<Arc<dyn Fn() -> SystemTime + Send + Sync>>,
+}
+
+/// Resolved user paths, keyed by everything they depend on besides the environment.
+type PathCache = HashMap<CacheKey, PathBuf>;
+
+/// The inputs of [`XdgContext::user_dir`] other than the environment: the parts of the basedir it uses, the settings of
+/// the context (which may differ between clones sharing the cache), and the suffix.
+#[derive(PartialEq, Eq, Hash)]
+struct CacheKey {
+    env_var: &'static str,
+    home_fallback: Option<&'static str>,
+    platform_var: Option<&'static str>,
+    no_home_fallback: bool,
+    tilde_expansion: bool,
+    systemd_dirs: bool,
+    fs_root: Option<PathBuf>,
+    suffix: PathBuf,
+}
+
+impl fmt::Debug for XdgContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("XdgContext")
+            .field("no_home_fallback", &self.no_home_fallback)
+            .field("etc_fallback", &self.etc_fallback)
+            .field("tilde_expansion", &self.tilde_expansion)
+            .field("secure_mode", &self.secure_mode)
+            .field("strict_empty_dirs", &self.strict_empty_dirs)
+            .field("fs_root", &self.fs_root)
+            .field("systemd_dirs", &self.systemd_dirs)
+            .field("length_check", &self.length_check)
+            .field("process_env", &self.env.is_none())
+            .field("cached", &self.cache.is_some())
+            .field("system_clock", &self.clock.is_none())
+            .finish()
+    }
+}
+
+impl XdgContext {
+    /// Create a context with the default (spec-following) behaviour.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Never fall back to the default location under `$HOME` for user-level paths.
+    /// Resolution then fails with [`Error::EnvVarNotSet`] unless the basedir's environment variable is explicitly set.
+    pub fn no_home_fallback(mut self) -> Self {
+        self.no_home_fallback = true;
+        self
+    }
+
+    /// Whether to include `/etc/<suffix>` as the lowest-precedence system path for `dirs::CONFIG`, after the paths from
+    /// `$XDG_CONFIG_DIRS` (or its default). This is for services following the traditional `/etc/<app>` convention.
+    pub fn with_etc_fallback(mut self, etc_fallback: bool) -> Self {
+        self.etc_fallback = etc_fallback;
+        self
+    }
+
+    /// Whether to expand a leading `~` in the user-level XDG variables (e.g. `XDG_CONFIG_HOME=~/config`) to `$HOME`.
+    /// A value of exactly `~` or `~/` refers to `$HOME` itself. Shells normally do this expansion, but not when the value
+    /// is quoted or set by other means, and the spec requires the value to be an absolute path.
+    pub fn with_tilde_expansion(mut self, tilde_expansion: bool) -> Self {
+        self.tilde_expansion = tilde_expansion;
+        self
+    }
+
+    /// Whether a system variable that is set but empty (e.g. `XDG_CONFIG_DIRS=`) means there are no system paths.
+    ///
+    /// By default (lenient), an empty or whitespace-only value is treated the same as the variable being unset, so the
+    /// default system paths are used. In strict mode, only an unset variable uses the defaults, and an empty value
+    /// produces an empty list of system paths.
+    pub fn with_strict_empty_dirs(mut self, strict_empty_dirs: bool) -> Self {
+        self.strict_empty_dirs = strict_empty_dirs;
+        self
+    }
+
+    /// Create directories with mode `0700` and files with mode `0600` in [`XdgContext::create_dir`] and
+    /// [`XdgContext::write`], instead of the defaults from the process umask. Use this for config or state holding secrets.
+    /// The permissions of existing directories and files are not changed. This has no effect on non-Unix platforms.
+    pub fn secure_mode(mut self) -> Self {
+        self.secure_mode = true;
+        self
+    }
+
+    /// Read environment variables from `env` instead of the process environment.
+    pub fn with_env(mut self, env: impl Env + Send + Sync + 'static) -> Self {
+        self.env = Some(Arc::new(env));
+        self
+    }
+
+    /// Whether to prefer the directories systemd provides to services over the XDG user dirs: `$STATE_DIRECTORY` for
+    /// `dirs::STATE` and `$CACHE_DIRECTORY` for `dirs::CACHE`, set by `StateDirectory=`/`CacheDirectory=` in the unit.
+    /// If the variable lists several directories, the first is used. The XDG resolution is used when they are not set.
+    ///
+    /// Note that these already refer to the service's own directory (e.g. `/var/lib/myservice`), so the suffix should
+    /// not repeat the application name.
+    pub fn with_systemd_dirs(mut self, systemd_dirs: bool) -> Self {
+        self.systemd_dirs = systemd_dirs;
+        self
+    }
+
+    /// Whether to check every resolved path, both user and system, with [`crate::check_path_max`], so that a suffix that
+    /// is too long (e.g. built from user input) fails resolution with [`Error::PathTooLong`] or [`Error::NameTooLong`]
+    /// rather than with an opaque OS error when the path is used.
+    pub fn with_length_check(mut self, length_check: bool) -> Self {
+        self.length_check = length_check;
+        self
+    }
+
+    /// Prefix every resolved path, both user and system, with `root`, e.g. to inspect another install mounted at `root`.
+    /// Combine this with [`XdgContext::with_env`] (e.g. with an [`crate::XdgEnv`]) to resolve with that install's
+    /// environment rather than the environment of this process.
+    pub fn with_fs_root(mut self, root: PathBuf) -> Self {
+        self.fs_root = Some(root);
+        self
+    }
+
+    /// Apply the check from [`XdgContext::with_length_check`] to a resolved path, if it is enabled.
+    fn check_length(&self, path: PathBuf) -> Result<PathBuf, Error> {
+        if self.length_check {
+            crate::check_path_max(path)
+        } else {
+            Ok(path)
+        }
+    }
+
+    /// Apply the root from [`XdgContext::with_fs_root`] to a resolved path.
+    fn rooted(&self, path: PathBuf) -> PathBuf {
+        match &self.fs_root {
+            Some(root) => root.join(path.strip_prefix("/").unwrap_or(&path)),
+            None => path,
+        }
+    }
+
+    /// Cache the paths resolved by [`XdgContext::user_dir`], so that resolving the same basedir and suffix again does not
+    /// re-read the environment. Clones of this context share the cache, and paths are cached separately for each basedir
+    /// and setting of the context, but clones must not read from a different environment (see [`XdgContext::with_env`]).
+    ///
+    /// This only caches path resolution, not anything about the filesystem (e.g. whether the path exists). Call
+    /// [`XdgContext::invalidate`] after changing the environment.
+    pub fn with_cache(mut self) -> Self {
+        self.cache = Some(Arc::default());
+        self
+    }
+
+    /// Clear the cache enabled with [`XdgContext::with_cache`], so paths are resolved from the environment again.
+    pub fn invalidate(&self) {
+        if let Some(cache) = &self.cache {
+            cache.lock().unwrap_or_else(PoisonError::into_inner).clear();
+        }
+    }
+
+    /// Use `clock` as the current time in time-dependent functions such as [`XdgContext::cache_fresh`] instead of the
+    /// system clock, e.g. to test them deterministically.
+    pub fn with_clock(mut self, clock: impl Fn() -> SystemTime + Send + Sync + 'static) -> Self {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
+    /// The current time according to this context's clock.
+    #[cfg(feature = "fs")]
+    fn now(&self) -> SystemTime {
+        match &self.clock {
+            Some(clock) => clock(),
+            None => SystemTime::now(),
+        }
+    }
+
+    /// Read an environment variable from this context's environment.
+    pub(crate) fn var_os(&self, key: &str) -> Option<OsString> {
+        match &self.env {
+            Some(env) => env.var_os(key),
+            None => ProcessEnv.var_os(key),
+        }
+    }
+
+    /// Read an environment variable, treating a whitespace-only value the same as it not being set.
+    pub(crate) fn env_value(&self, key: &str) -> Option<OsString> {
+        self.var_os(key)
+            .filter(|val| !val.to_str().is_some_and(|s| s.trim().is_empty()))
+    }
+
+    /// The user's home directory: the first of the platform's home variables that is set to an absolute path.
+    ///
+    /// On Windows these are `HOME`, then `USERPROFILE`, then `HOMEDRIVE` and `HOMEPATH` combined. Everywhere else, only
+    /// `HOME` is used.
+    pub(crate) fn home_dir(&self) -> Option<PathBuf> {
+        let var = |key| self.env_value(key).map(PathBuf::from);
+
+        #[cfg(windows)]
+        let candidates = [
+            var("HOME"),
+            var("USERPROFILE"),
+            var("HOMEDRIVE").zip(var("HOMEPATH")).map(|(drive, path)| {
+                let mut home = drive.into_os_string();
+                home.push(path);
+                PathBuf::from(home)
+            }),
+        ];
+        #[cfg(not(windows))]
+        let candidates = [var("HOME")];
+
+        candidates.into_iter().flatten().find(|p| p.is_absolute())
+    }
+
+    /// Returns the user-path of a given XDG basedir, as with [`crate::xdg_user_dir`].
+    pub fn user_dir(&self, xdg_dir: &XdgDir, suffix: impl AsRef<Path>) -> Result<PathBuf, Error> {
+        let suffix = suffix.as_ref();
+        let Some(cache) = &self.cache else {
+            return self.user_dir_os(xdg_dir, suffix.as_os_str());
+        };
+
+        let key = CacheKey {
+            env_var: xdg_dir.env_var,
+            home_fallback: xdg_dir.home_fallback,
+            platform_var: xdg_dir.platform_var,
+            no_home_fallback: self.no_home_fallback,
+            tilde_expansion: self.tilde_expansion,
+            systemd_dirs: self.systemd_dirs,
+            fs_root: self.fs_root.clone(),
+            suffix: suffix.to_path_buf(),
+        };
+        if let Some(path) = cache
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&key)
+        {
+            return Ok(path.clone());
+        }
+
+        // Errors are not cached, they are cheap to produce again
+        let path = self.user_dir_os(xdg_dir, suffix.as_os_str())?;
+        cache
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(key, path.clone());
+        Ok(path)
+    }
+
+    /// Returns the user-path of a given XDG basedir with an arbitrary `OsStr` suffix, as with [`crate::xdg_user_dir_os`].
+    pub fn user_dir_os(&self, xdg_dir: &XdgDir, suffix: &OsStr) -> Result<PathBuf, Error> {
+        debug::dump_once(self);
+
+        let home_fallback = if self.no_home_fallback {
+            None
+        } else {
+            xdg_dir.home_fallback
+        };
+
+        if self.systemd_dirs
+            && let Some(dir) = self.systemd_dir(xdg_dir)
+        {
+            let mut path = dir;
+            push_suffix(&mut path, suffix);
+            return self.check_length(self.rooted(path));
+        }
+
+        let env_path = match self.env_value(xdg_dir.env_var) {
+            Some(p) => match Path::new(&p).strip_prefix("~") {
+                Ok(rest) if self.tilde_expansion => {
+                    let mut home_path = self.home_dir().ok_or(Error::NoHome)?;
+                    // `~` alone is $HOME itself, avoid adding a trailing separator for it
+                    if !rest.as_os_str().is_empty() {
+                        home_path.push(rest);
+                    }
+                    Some(home_path)
+                }
+                _ => Some(PathBuf::from(p)),
+            },
+            None => None,
+        };
+
+        let config_path = match env_path.filter(|p| p.is_absolute()) {
+            // Check the normal environment variable first. The spec says relative paths are invalid and must be ignored.
+            Some(p) => Ok(p),
+            // If not set, check the platform's native location (if any), then the default value under $HOME (or return
+            // error if that doesn't apply)
+            None if let Some(p) = self.platform_dir(xdg_dir) => Ok(p),
+            None => match home_fallback {
+                Some(home_dir) => self
+                    .home_dir()
+                    .map(|mut home_path| {
+                        home_path.push(home_dir);
+                        home_path
+                    })
+                    .ok_or(Error::NoHome),
+                None => Err(Error::EnvVarNotSet(xdg_dir.env_var)),
+            },
+        };
+
+        config_path.and_then(|mut path| {
+            push_suffix(&mut path, suffix);
+            self.check_length(self.rooted(path))
+        })
+    }
+
+    /// The native location of a given XDG basedir from [`XdgDir::platform_var`], if there is one and it is set to an
+    /// absolute path. Like the fallback under `$HOME`, this is not used with [`XdgContext::no_home_fallback`].
+    fn platform_dir(&self, xdg_dir: &XdgDir) -> Option<PathBuf> {
+        if self.no_home_fallback {
+            return None;
+        }
+
+        let value = self.env_value(xdg_dir.platform_var?)?;
+        Some(PathBuf::from(value)).filter(|p| p.is_absolute())
+    }
+
+    /// The first directory from the systemd-provided variable for a given XDG basedir, if there is one and it is set.
+    fn systemd_dir(&self, xdg_dir: &XdgDir) -> Option<PathBuf> {
+        let var = if xdg_dir.env_var == dirs::STATE.env_var {
+            "STATE_DIRECTORY"
+        } else if xdg_dir.env_var == dirs::CACHE.env_var {
+            "CACHE_DIRECTORY"
+        } else {
+            return None;
+        };
+
+        let value = self.env_value(var)?;
+        split_paths(&value).next().map(PathBuf::from)
+    }
+
+    /// Returns the list of system paths for a given XDG basedir, as with [`crate::xdg_system_dirs`].
+    pub fn system_dirs(
+        &self,
+        xdg_dir: &XdgDir,
+        suffix: impl AsRef<Path>,
+    ) -> Result<Vec<PathBuf>, Error> {
+        self.system_dirs_tagged(xdg_dir, suffix)
+            .map(|paths| paths.into_iter().map(|(path, _)| path).collect())
+    }
+
+    /// Returns the list of system paths for a given XDG basedir along with where each came from,
+    /// as with [`crate::xdg_system_dirs_tagged`].
+    pub fn system_dirs_tagged(
+        &self,
+        xdg_dir: &XdgDir,
+        suffix: impl AsRef<Path>,
+    ) -> Result<Vec<(PathBuf, SysDirSource)>, Error> {
+        let suffix = suffix.as_ref();
+        debug::dump_once(self);
+
+        let mut paths = self.spec_system_dirs_tagged(xdg_dir, suffix)?;
+
+        if self.etc_fallback && xdg_dir.env_var == dirs::CONFIG.env_var {
+            let mut etc_path = PathBuf::from("/etc");
+            push_suffix(&mut etc_path, suffix);
+            if !paths.iter().any(|(p, _)| *p == etc_path) {
+                paths.push((etc_path, SysDirSource::Etc));
+            }
+        }
+
+        paths
+            .into_iter()
+            .map(|(path, source)| Ok((self.check_length(self.rooted(path))?, source)))
+            .collect()
+    }
+
+    /// The system paths for a given XDG basedir as defined by the spec, without any extensions from the context.
+    fn spec_system_dirs_tagged(
+        &self,
+        xdg_dir: &XdgDir,
+        suffix: &Path,
+    ) -> Result<Vec<(PathBuf, SysDirSource)>, Error> {
+        // Parse the env var, if it is set
+        // Note: this follows the same format as PATH, which does not allow for any escaping or quoting of the separator
+        if let Some(var) = xdg_dir.system_var
+            && self.strict_empty_dirs
+            && self.var_os(var).is_some()
+            && self.env_value(var).is_none()
+        {
+            // Set, but empty
+            return Ok(vec![]);
+        }
+        if let Some(var) = xdg_dir.system_var
+            && let Some(val) = self.env_value(var)
+        {
+            // The spec says relative paths are invalid and must be ignored. Repeated entries are only kept the first time.
+            let mut seen = HashSet::new();
+            let paths: Vec<_> = split_paths(&val)
+                .map(PathBuf::from)
+                .filter(|path| path.is_absolute() && seen.insert(path.clone()))
+                .map(|mut path| {
+                    push_suffix(&mut path, suffix);
+                    (path, SysDirSource::EnvVar)
+                })
+                .collect();
+
+            // If every entry is invalid, treat it the same as not being set
+            if !paths.is_empty() {
+                return Ok(paths);
+            }
+        }
+
+        // If the env var is not set, fall back to the default
+        if let Some(paths) = xdg_dir.system_fallback {
+            return Ok(paths
+                .iter()
+                .map(|p| {
+                    let mut path = PathBuf::from(p);
+                    push_suffix(&mut path, suffix);
+                    (path, SysDirSource::Fallback)
+                })
+                .collect());
+        }
+
+        // Otherwise, there's either nothing set or there is not intended to be a system-level fallback
+        Err(match xdg_dir.system_var {
+            Some(var) => Error::EnvVarNotSet(var),
+            None => Error::SystemDirNotApplicable(xdg_dir.description),
+        })
+    }
+
+    /// Returns every candidate path for a given XDG basedir in precedence order, as with [`crate::xdg_candidate_paths`].
+    pub fn candidate_paths(&self, xdg_dir: &XdgDir, suffix: impl AsRef<Path>) -> Vec<PathBuf> {
+        let suffix = suffix.as_ref();
+        let mut seen = HashSet::new();
+
+        self.user_dir(xdg_dir, suffix)
+            .into_iter()
+            .chain(self.system_dirs(xdg_dir, suffix).into_iter().flatten())
+            .filter(|p| seen.insert(dedup_key(p)))
+            .collect()
+    }
+
+    /// Find the first location where `suffix` exists for a given XDG basedir, as with [`crate::xdg_location_of`].
+    #[cfg(feature = "fs")]
+    pub fn location_of(
+        &self,
+        xdg_dir: &XdgDir,
+        suffix: impl AsRef<Path>,
+    ) -> Result<PathBuf, Error> {
+        self.location_of_matching(xdg_dir, suffix, |_| true)
+    }
+
+    /// Search for the first candidate that exists and whose metadata (following symlinks) satisfies `matches`, as with
+    /// [`XdgContext::location_of`]. If nothing is found but a candidate could not be checked for lack of permission,
+    /// this returns [`Error::PermissionDenied`] for the first such candidate.
+    #[cfg(feature = "fs")]
+    pub(crate) fn location_of_matching(
+        &self,
+        xdg_dir: &XdgDir,
+        suffix: impl AsRef<Path>,
+        matches: impl Fn(&Metadata) -> bool,
+    ) -> Result<PathBuf, Error> {
+        // The first candidate that could not be checked for lack of permission, reported if nothing is found
+        let denied = RefCell::new(None);
+
+        let found = self.location_of_with(xdg_dir, suffix, |p| match p.metadata() {
+            Ok(meta) => matches(&meta),
+            Err(e) => {
+                if e.kind() == io::ErrorKind::PermissionDenied {
+                    denied.borrow_mut().get_or_insert_with(|| p.to_path_buf());
+                }
+                false
+            }
+        });
+
+        match (found, denied.into_inner()) {
+            (Err(Error::NotFound(..)), Some(path)) => Err(Error::PermissionDenied(path)),
+            (found, _) => found.map(|p| p.canonicalize().unwrap_or(p)),
+        }
+    }
+
+    /// Create the user-level directory `suffix` for a given XDG basedir along with any missing parents, as with
+    /// [`crate::xdg_create_dir`]. Returns the path of the directory.
+    #[cfg(feature = "fs")]
+    pub fn create_dir(&self, xdg_dir: &XdgDir, suffix: &str) -> Result<PathBuf, Error> {
+        let path = self.user_dir(xdg_dir, suffix)?;
+        if path.metadata().is_ok_and(|meta| !meta.is_dir()) {
+            return Err(Error::NotADirectory(path));
+        }
+
+        self.dir_builder().create(&path).map_err(io_error(&path))?;
+        Ok(path)
+    }
+
+    /// Write `contents` to the user-level file `suffix` for a given XDG basedir, creating any missing parent directories
+    /// and replacing the file if it exists, as with [`crate::xdg_write`]. Returns the path of the file.
+    #[cfg(feature = "fs")]
+    pub fn write(
+        &self,
+        xdg_dir: &XdgDir,
+        suffix: &str,
+        contents: impl AsRef<[u8]>,
+    ) -> Result<PathBuf, Error> {
+        let path = self.user_dir(xdg_dir, suffix)?;
+        if let Some(parent) = path.parent() {
+            self.dir_builder()
+                .create(parent)
+                .map_err(io_error(parent))?;
+        }
+
+        let mut options = File::options();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        if self.secure_mode {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+
+        options
+            .open(&path)
+            .and_then(|mut file| file.write_all(contents.as_ref()))
+            .map_err(io_error(&path))?;
+        Ok(path)
+    }
+
+    /// A recursive `DirBuilder` applying this context's permissions.
+    #[cfg(feature = "fs")]
+    fn dir_builder(&self) -> DirBuilder {
+        let mut builder = DirBuilder::new();
+        builder.recursive(true);
+        #[cfg(unix)]
+        if self.secure_mode {
+            use std::os::unix::fs::DirBuilderExt;
+            builder.mode(0o700);
+        }
+        builder
+    }
+
+    /// Whether the user cache file `suffix` exists and was modified at most `max_age` ago, as with
+    /// [`crate::xdg_cache_fresh`].
+    #[cfg(feature = "fs")]
+    pub fn cache_fresh(&self, suffix: &str, max_age: Duration) -> bool {
+        self.user_dir(&dirs::CACHE, suffix)
+            .and_then(|path| {
+                path.metadata()
+                    .and_then(|meta| meta.modified())
+                    .map_err(io_error(&path))
+            })
+            .is_ok_and(|modified| self.age(modified) <= max_age)
+    }
+
+    /// Every existing candidate location of `suffix` with how long ago it was modified, most recently modified first, as
+    /// with [`crate::xdg_locations_by_mtime`].
+    #[cfg(feature = "fs")]
+    pub fn locations_by_mtime(&self, xdg_dir: &XdgDir, suffix: &str) -> Vec<(PathBuf, Duration)> {
+        let mut locations: Vec<_> = self
+            .candidate_paths(xdg_dir, suffix)
+            .into_iter()
+            .filter_map(|path| {
+                let modified = path.metadata().and_then(|meta| meta.modified()).ok()?;
+                Some((path, self.age(modified)))
+            })
+            .collect();
+
+        // Stable, so candidates modified at the same time stay in precedence order
+        locations.sort_by_key(|(_, age)| *age);
+        locations
+    }
+
+    /// How long ago `time` was according to this context's clock, or zero if it is in the future.
+    #[cfg(feature = "fs")]
+    fn age(&self, time: SystemTime) -> Duration {
+        self.now().duration_since(time).unwrap_or_default()
+    }
+
+    /// Find the first location where `suffix` exists according to `exists`, as with [`crate::xdg_location_of_with`].
+    pub fn location_of_with(
+        &self,
+        xdg_dir: &XdgDir,
+        suffix: impl AsRef<Path>,
+        exists: impl Fn(&Path) -> bool,
+    ) -> Result<PathBuf, Error> {
+        let suffix = suffix.as_ref();
+        let mut checked = vec![];
+
+        // Check user location first, then system locations if not present in the user location
+        for p in self.candidate_paths(xdg_dir, suffix) {
+            checked.push(p.to_string_lossy().to_string());
+            if exists(&p) {
+                return Ok(p);
+            }
+        }
+
+        // Didn't find it
+        Err(Error::NotFound(suffix.display().to_string(), checked))
+    }
+}
+
+/// The separator of lists of paths such as `$XDG_DATA_DIRS`: `:` as in the spec, or `;` on Windows with the
+/// `cross-platform` feature to match `PATH` there.
+pub(crate) const LIST_SEPARATOR: char = if cfg!(all(feature = "cross-platform", windows)) {
+    ';'
+} else {
+    ':'
+};
+
+/// Split a list of paths separated by [`LIST_SEPARATOR`], as in `$XDG_DATA_DIRS`. This works on the raw bytes, so paths
+/// that are not valid unicode are preserved.
+fn split_paths(val: &OsStr) -> impl Iterator<Item = &OsStr> {
+    val.as_encoded_bytes()
+        .split(|&b| b == LIST_SEPARATOR as u8)
+        // Safety: the bytes come from `as_encoded_bytes`, and are only split around an ASCII character
+        .map(|p| unsafe { OsStr::from_encoded_bytes_unchecked(p) })
+}
+
+/// Append `suffix` to `path` as a relative path, without any redundant separators: leading `/` or `./`, trailing
+/// separators, and repeated separators in the suffix are dropped (e.g. `/app`, `./app` and `app/` all append just `app`).
+/// An empty suffix appends nothing, and any trailing separator of `path` is dropped so that the result is the base
+/// directory itself (e.g. `$XDG_CONFIG_HOME=/etc/xdg/` gives `/etc/xdg`).
+pub(crate) fn push_suffix(path: &mut PathBuf, suffix: impl AsRef<Path>) {
+    let suffix: PathBuf = suffix
+        .as_ref()
+        .components()
+        .filter(|c| matches!(c, Component::Normal(_) | Component::ParentDir))
+        .collect();
+    if suffix.as_os_str().is_empty() {
+        *path = path.components().collect();
+    } else {
+        path.push(suffix);
+    }
+}
+
+/// Key identifying the location a candidate path refers to, for de-duplicating candidates.
+/// This is the canonical path if it exists, or otherwise the canonical parent joined with the final component, so that
+/// e.g. the same base directory given through different symlinks is recognized even when the suffix does not exist yet.
+#[cfg(feature = "fs")]
+pub(crate) fn dedup_key(path: &Path) -> PathBuf {
+    if let Ok(canonical) = path.canonicalize() {
+        return canonical;
+    }
+
+    match (path.parent().map(Path::canonicalize), path.file_name()) {
+        (Some(Ok(parent)), Some(name)) => parent.join(name),
+        _ => path.components().collect(),
+    }
+}
+
+/// Key identifying the location a candidate path refers to, for de-duplicating candidates.
+/// Without filesystem access this can only normalize the path lexically.
+#[cfg(not(feature = "fs"))]
+pub(crate) fn dedup_key(path: &Path) -> PathBuf {
+    path.components().collect()
+}
@@ -0,0 +1,113 @@
+//! Diagnostic output enabled by setting `XDG_DIRS_DEBUG=1`, for debugging path resolution without code changes.
+
+use std::{
+    io::{self, IsTerminal, Write},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::{Env, ProcessEnv, XdgContext, dirs};
+
+/// The environment variable enabling the debug dump.
+const DEBUG_VAR: &str = "XDG_DIRS_DEBUG";
+
+/// Whether the dump has already been printed (or is being printed).
+static DUMPED: AtomicBool = AtomicBool::new(false);
+
+/// Whether to highlight the dump with ANSI escapes. This is only done when stderr is a terminal and `NO_COLOR` is not
+/// set, so piped or redirected output stays plain text.
+fn styled() -> bool {
+    io::stderr().is_terminal()
+        && ProcessEnv
+            .var_os("NO_COLOR")
+            .is_none_or(|val| val.is_empty())
+}
+
+/// If `XDG_DIRS_DEBUG=1` is set in the process environment (as read by [`ProcessEnv`]), print how every XDG basedir
+/// resolves with `ctx` to stderr. This only happens the first time it is called with the variable set.
+pub(crate) fn dump_once(ctx: &XdgContext) {
+    let enabled = ProcessEnv.var_os(DEBUG_VAR).is_some_and(|val| val == "1");
+    dump_once_to(ctx, enabled, &DUMPED, &mut io::stderr(), styled());
+}
+
+/// Write the dump for `ctx` to `out` if `enabled`, unless `dumped` is already set, and set it.
+fn dump_once_to(
+    ctx: &XdgContext,
+    enabled: bool,
+    dumped: &AtomicBool,
+    out: &mut impl Write,
+    styled: bool,
+) {
+    if !enabled || dumped.swap(true, Ordering::Relaxed) {
+        return;
+    }
+
+    let (bold, reset) = if styled {
+        ("\x1b[1m", "\x1b[0m")
+    } else {
+        ("", "")
+    };
+
+    // Failing to write diagnostics is not worth reporting
+    for xdg_dir in dirs::ALL {
+        let _ = writeln!(out, "xdg-dirs: {bold}{}{reset}", xdg_dir.env_var);
+        let _ = match ctx.user_dir(xdg_dir, "") {
+            Ok(path) => writeln!(out, "xdg-dirs:   user: {}", path.display()),
+            Err(e) => writeln!(out, "xdg-dirs:   user: {e}"),
+        };
+        match ctx.system_dirs_tagged(xdg_dir, "") {
+            Ok(paths) => {
+                for (path, source) in paths {
+                    let _ = writeln!(out, "xdg-dirs:   system: {} ({source:?})", path.display());
+                }
+            }
+            Err(e) => {
+                let _ = writeln!(out, "xdg-dirs:   system: {e}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_dump_once_to() {
+        let env = HashMap::from([
+            ("HOME", "/home/user"),
+            ("XDG_STATE_HOME", "/state"),
+            ("XDG_DATA_DIRS", "/share"),
+        ]);
+        let ctx = XdgContext::new().with_env(env);
+        let dumped = AtomicBool::new(false);
+        let mut out = vec![];
+
+        // Nothing is written, or marked as dumped, unless enabled
+        dump_once_to(&ctx, false, &dumped, &mut out, false);
+        assert!(out.is_empty());
+
+        dump_once_to(&ctx, true, &dumped, &mut out, false);
+        let dump = String::from_utf8(out.clone()).unwrap();
+        assert!(dump.contains("xdg-dirs: XDG_STATE_HOME\nxdg-dirs:   user: /state\n"));
+        assert!(dump.contains("xdg-dirs:   user: /home/user/.config\n"));
+        assert!(dump.contains("xdg-dirs:   system: /share (EnvVar)\n"));
+        assert!(dump.contains("xdg-dirs:   system: /etc/xdg (Fallback)\n"));
+        assert!(dump.contains("xdg-dirs: XDG_RUNTIME_DIR\nxdg-dirs:   user: "));
+        assert!(!dump.contains('\x1b'));
+        assert_eq!(dump.matches("xdg-dirs: XDG_CONFIG_HOME").count(), 1);
+
+        // Only dumped once
+        dump_once_to(&ctx, true, &dumped, &mut out, false);
+        assert_eq!(String::from_utf8(out).unwrap(), dump);
+
+        let mut out = vec![];
+        dump_once_to(&ctx, true, &AtomicBool::new(false), &mut out, true);
+        assert!(
+            String::from_utf8(out)
+                .unwrap()
+                .contains("\x1b[1mXDG_CONFIG_HOME\x1b[0m")
+        );
+    }
+}
@@ -0,0 +1,95 @@
+//! Comparing how two environments resolve the same XDG path, for diagnosing differences between machines.
+
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+use crate::{Env, Error, SysDirSource, XdgContext, XdgDir};
+
+/// Why two environments resolved an XDG path differently.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffReason {
+    /// The variable is set in both environments, but to different values.
+    EnvVar(&'static str),
+    /// The variable is set in one environment but not the other, so only one of them used the default.
+    Fallback(&'static str),
+}
+
+/// The result of [`diff_resolution`]: how each environment resolved the path, and why they differ.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolutionDiff {
+    /// The user-level path in the first environment
+    pub user_a: Result<PathBuf, Error>,
+    /// The user-level path in the second environment
+    pub user_b: Result<PathBuf, Error>,
+    /// The system-level paths in the first environment
+    pub system_a: Result<Vec<(PathBuf, SysDirSource)>, Error>,
+    /// The system-level paths in the second environment
+    pub system_b: Result<Vec<(PathBuf, SysDirSource)>, Error>,
+    /// The environment differences that caused the paths to differ. Empty if they resolve the same.
+    pub reasons: Vec<DiffReason>,
+}
+
+impl ResolutionDiff {
+    /// Whether both environments resolve to the same user and system paths.
+    pub fn is_same(&self) -> bool {
+        self.user_a == self.user_b && self.system_a == self.system_b
+    }
+}
+
+/// Compare how the environments `env_a` and `env_b` resolve `suffix` for the given XDG basedir.
+pub fn diff_resolution(
+    env_a: impl Env + Send + Sync + 'static,
+    env_b: impl Env + Send + Sync + 'static,
+    xdg_dir: &XdgDir,
+    suffix: &str,
+) -> ResolutionDiff {
+    let a = XdgContext::new().with_env(env_a);
+    let b = XdgContext::new().with_env(env_b);
+
+    let user_a = a.user_dir(xdg_dir, suffix);
+    let user_b = b.user_dir(xdg_dir, suffix);
+    let system_a = a.system_dirs_tagged(xdg_dir, suffix);
+    let system_b = b.system_dirs_tagged(xdg_dir, suffix);
+
+    let mut reasons = vec![];
+    let mut compare = |var: &'static str| {
+        if let Some(reason) = var_reason(var, a.env_value(var), b.env_value(var)) {
+            reasons.push(reason);
+        }
+    };
+
+    if user_a != user_b {
+        compare(xdg_dir.env_var);
+        // $HOME only matters when at least one side falls back to the default location under it
+        let falls_back = |ctx: &XdgContext| {
+            ctx.env_value(xdg_dir.env_var)
+                .is_none_or(|val| !Path::new(&val).is_absolute())
+        };
+        if xdg_dir.home_fallback.is_some() && (falls_back(&a) || falls_back(&b)) {
+            compare("HOME");
+        }
+    }
+    if system_a != system_b
+        && let Some(var) = xdg_dir.system_var
+    {
+        compare(var);
+    }
+
+    ResolutionDiff {
+        user_a,
+        user_b,
+        system_a,
+        system_b,
+        reasons,
+    }
+}
+
+fn var_reason(var: &'static str, a: Option<OsString>, b: Option<OsString>) -> Option<DiffReason> {
+    match (a, b) {
+        (Some(a), Some(b)) if a != b => Some(DiffReason::EnvVar(var)),
+        (Some(_), None) | (None, Some(_)) => Some(DiffReason::Fallback(var)),
+        _ => None,
+    }
+}
@@ -0,0 +1,188 @@
+//! Abstraction over where environment variables are read from, so resolution can be done against something other than
+//! the process environment (e.g. in tests, or to compare environments).
+
+#[cfg(feature = "virtual-env")]
+use std::sync::OnceLock;
+use std::{
+    borrow::Borrow,
+    collections::HashMap,
+    env,
+    ffi::{OsStr, OsString},
+    hash::{BuildHasher, Hash},
+    path::PathBuf,
+};
+
+use crate::{Error, XdgContext, XdgDir};
+
+/// A source of environment variables, used with [`XdgContext::with_env`] to resolve paths without reading (or needing to
+/// modify) the process environment, e.g. so tests can run in parallel.
+///
+/// ```
+/// use std::{collections::HashMap, path::PathBuf};
+/// use xdg_dirs::{XdgContext, dirs};
+///
+/// let env = HashMap::from([("HOME", "/home/user")]);
+/// let ctx = XdgContext::new().with_env(env);
+/// assert_eq!(
+///     ctx.user_dir(&dirs::CONFIG, "app"),
+///     Ok(PathBuf::from("/home/user/.config/app"))
+/// );
+/// ```
+pub trait Env {
+    /// Get the value of the variable `key`, or `None` if it is not set.
+    fn var_os(&self, key: &str) -> Option<OsString>;
+
+    /// Get the value of the variable `key`, or `None` if it is not set or not valid unicode.
+    fn get(&self, key: &str) -> Option<String> {
+        self.var_os(key)?.into_string().ok()
+    }
+}
+
+/// The environment of the current process, read with [`std::env::var_os`]. With the `virtual-env` feature, the
+/// variables registered with `set_virtual_env` are read instead once they are registered.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcessEnv;
+
+impl Env for ProcessEnv {
+    fn var_os(&self, key: &str) -> Option<OsString> {
+        #[cfg(feature = "virtual-env")]
+        if let Some(vars) = VIRTUAL_ENV.get() {
+            return vars.get(key).map(OsString::from);
+        }
+
+        env::var_os(key)
+    }
+}
+
+/// The variables registered with [`set_virtual_env`].
+#[cfg(feature = "virtual-env")]
+static VIRTUAL_ENV: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+/// Register `vars` as the environment for the whole process, in place of the real one, for hosts where it is unavailable
+/// or not how configuration is provided (e.g. WASM). From then on the free functions, and any [`XdgContext`] without
+/// its own [`XdgContext::with_env`], only read variables from `vars`.
+///
+/// This can only be done once, ideally at startup. If variables were already registered, `vars` is returned unused.
+#[cfg(feature = "virtual-env")]
+pub fn set_virtual_env(vars: HashMap<String, String>) -> Result<(), HashMap<String, String>> {
+    VIRTUAL_ENV.set(vars)
+}
+
+/// A fixed set of variables, e.g. `HashMap::from([("HOME", "/home/user")])`.
+impl<K, V, S> Env for HashMap<K, V, S>
+where
+    K: Borrow<str> + Hash + Eq,
+    V: AsRef<OsStr>,
+    S: BuildHasher,
+{
+    fn var_os(&self, key: &str) -> Option<OsString> {
+        self.get(key).map(|v| v.as_ref().to_os_string())
+    }
+}
+
+/// An explicit value for every variable used in XDG path resolution, for fully reproducible resolution (e.g. in tests or
+/// sandboxes). A field set to `None` is treated as that variable being unset; the process environment is never read.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct XdgEnv {
+    /// `$HOME`
+    pub home: Option<PathBuf>,
+    /// `$XDG_CONFIG_HOME`
+    pub config_home: Option<PathBuf>,
+    /// `$XDG_DATA_HOME`
+    pub data_home: Option<PathBuf>,
+    /// `$XDG_CACHE_HOME`
+    pub cache_home: Option<PathBuf>,
+    /// `$XDG_STATE_HOME`
+    pub state_home: Option<PathBuf>,
+    /// `$XDG_RUNTIME_DIR`
+    pub runtime_dir: Option<PathBuf>,
+    /// `$XDG_BIN_HOME`
+    pub bin_home: Option<PathBuf>,
+    /// `$XDG_CONFIG_DIRS`, as a `:`-separated list
+    pub config_dirs: Option<OsString>,
+    /// `$XDG_DATA_DIRS`, as a `:`-separated list
+    pub data_dirs: Option<OsString>,
+    /// `%APPDATA%`, used by the `cross-platform` feature on Windows
+    pub app_data: Option<PathBuf>,
+    /// `%LOCALAPPDATA%`, used by the `cross-platform` feature on Windows
+    pub local_app_data: Option<PathBuf>,
+    /// `$STATE_DIRECTORY`, used with [`XdgContext::with_systemd_dirs`], as a `:`-separated list
+    pub state_directory: Option<OsString>,
+    /// `$CACHE_DIRECTORY`, used with [`XdgContext::with_systemd_dirs`], as a `:`-separated list
+    pub cache_directory: Option<OsString>,
+}
+
+impl XdgEnv {
+    /// Snapshot the variables from the process environment (as read by [`ProcessEnv`]). Resolving against the snapshot
+    /// (with [`XdgContext::with_env`]) gives the same results as the process environment for the predefined basedirs,
+    /// consistently across a batch of lookups, and avoids re-reading the environment for each of them, even if it is
+    /// modified in the meantime. Variables of custom basedirs (see [`XdgDir::new`]) are not captured.
+    ///
+    /// `home` is the home directory as resolved for the platform (e.g. from `USERPROFILE` on Windows). Every other value
+    /// is kept as-is, including values that are not valid unicode.
+    ///
+    /// ```
+    /// use xdg_dirs::{XdgContext, XdgEnv, dirs};
+    ///
+    /// let ctx = XdgContext::new().with_env(XdgEnv::from_env());
+    /// let user = ctx.user_dir(&dirs::DATA, "app");
+    /// let system = ctx.system_dirs(&dirs::DATA, "app");
+    /// ```
+    pub fn from_env() -> Self {
+        let var = |key| ProcessEnv.var_os(key);
+        let path = |key| var(key).map(PathBuf::from);
+
+        XdgEnv {
+            home: XdgContext::new().home_dir(),
+            config_home: path("XDG_CONFIG_HOME"),
+            data_home: path("XDG_DATA_HOME"),
+            cache_home: path("XDG_CACHE_HOME"),
+            state_home: path("XDG_STATE_HOME"),
+            runtime_dir: path("XDG_RUNTIME_DIR"),
+            bin_home: path("XDG_BIN_HOME"),
+            config_dirs: var("XDG_CONFIG_DIRS"),
+            data_dirs: var("XDG_DATA_DIRS"),
+            app_data: path("APPDATA"),
+            local_app_data: path("LOCALAPPDATA"),
+            state_directory: var("STATE_DIRECTORY"),
+            cache_directory: var("CACHE_DIRECTORY"),
+        }
+    }
+
+    /// Returns the user-path of a given XDG basedir as with [`crate::xdg_user_dir`], using only these variables.
+    pub fn resolve_user(&self, xdg_dir: &XdgDir, suffix: &str) -> Result<PathBuf, Error> {
+        XdgContext::new()
+            .with_env(self.clone())
+            .user_dir(xdg_dir, suffix)
+    }
+
+    /// Returns the list of system paths of a given XDG basedir as with [`crate::xdg_system_dirs`], using only these
+    /// variables.
+    pub fn resolve_system(&self, xdg_dir: &XdgDir, suffix: &str) -> Result<Vec<PathBuf>, Error> {
+        XdgContext::new()
+            .with_env(self.clone())
+            .system_dirs(xdg_dir, suffix)
+    }
+}
+
+impl Env for XdgEnv {
+    fn var_os(&self, key: &str) -> Option<OsString> {
+        let path = |p: &Option<PathBuf>| p.clone().map(PathBuf::into_os_string);
+        match key {
+            "HOME" => path(&self.home),
+            "XDG_CONFIG_HOME" => path(&self.config_home),
+            "XDG_DATA_HOME" => path(&self.data_home),
+            "XDG_CACHE_HOME" => path(&self.cache_home),
+            "XDG_STATE_HOME" => path(&self.state_home),
+            "XDG_RUNTIME_DIR" => path(&self.runtime_dir),
+            "XDG_BIN_HOME" => path(&self.bin_home),
+            "XDG_CONFIG_DIRS" => self.config_dirs.clone(),
+            "XDG_DATA_DIRS" => self.data_dirs.clone(),
+            "APPDATA" => path(&self.app_data),
+            "LOCALAPPDATA" => path(&self.local_app_data),
+            "STATE_DIRECTORY" => self.state_directory.clone(),
+            "CACHE_DIRECTORY" => self.cache_directory.clone(),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,61 @@
+//! Helpers for simple `KEY=VALUE` config files, such as `locale.conf`.
+
+use std::{collections::HashMap, fs};
+
+use crate::{XdgContext, dirs, xdg_existing_candidates};
+
+/// Parse `KEY=VALUE` lines, in order. Blank lines, `#` comments, and lines without `=` are skipped, whitespace around
+/// keys and values is trimmed, and a value wrapped in matching single or double quotes has them removed.
+pub(crate) fn parse_key_values(contents: &str) -> Vec<(String, String)> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| {
+            let value = value.trim();
+            let value = ['"', '\'']
+                .iter()
+                .find_map(|&q| value.strip_prefix(q)?.strip_suffix(q))
+                .unwrap_or(value);
+            (key.trim().to_string(), value.to_string())
+        })
+        .collect()
+}
+
+/// Find `locale.conf` in the config search path, falling back to `/etc/locale.conf`, and parse its `KEY=VALUE` settings
+/// (e.g. `LANG`). Returns `None` if no `locale.conf` exists or it cannot be read.
+pub fn xdg_locale_conf() -> Option<HashMap<String, String>> {
+    let path = XdgContext::new()
+        .with_etc_fallback(true)
+        .location_of(&dirs::CONFIG, "locale.conf")
+        .ok()?;
+    let contents = fs::read_to_string(path).ok()?;
+
+    Some(parse_key_values(&contents).into_iter().collect())
+}
+
+/// Read every existing copy of the config file `suffix` across the config search path, parse each with `parse`, and merge
+/// them per key: a key set in a higher precedence file (e.g. under `$XDG_CONFIG_HOME`) wins over the same key in lower
+/// precedence ones (e.g. later entries of `$XDG_CONFIG_DIRS`), while keys only set in lower precedence files are kept.
+/// This is how e.g. `mimeapps.list` is combined. Within a single file, a later entry for a key overrides an earlier one.
+///
+/// Files that cannot be read are skipped.
+pub fn xdg_keyed_merge(
+    suffix: &str,
+    parse: impl Fn(&str) -> Vec<(String, String)>,
+) -> HashMap<String, String> {
+    let mut merged = HashMap::new();
+
+    for path in xdg_existing_candidates(&dirs::CONFIG, suffix) {
+        let Ok(contents) = fs::read_to_string(path) else {
+            continue;
+        };
+        let file: HashMap<_, _> = parse(&contents).into_iter().collect();
+        for (key, value) in file {
+            merged.entry(key).or_insert(value);
+        }
+    }
+
+    merged
+}
@@ -0,0 +1,204 @@
+//! Support for applications migrating from legacy dotfile locations (e.g. `~/.myapp`) to XDG basedirs.
+
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    Error, XdgContext, XdgDir, dirs, io_error, same_filesystem, xdg_location_of, xdg_user_dir,
+};
+
+/// The outcome of [`xdg_migrate_legacy`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrationResult {
+    /// The legacy path was moved to the XDG location
+    Moved { from: PathBuf, to: PathBuf },
+    /// The XDG location already exists so nothing was changed, even if the legacy path also exists
+    AlreadyExists(PathBuf),
+    /// There is no legacy path to migrate
+    NoLegacy,
+}
+
+/// Find a config file or directory, preferring the XDG location of `suffix` and otherwise using the legacy `$HOME/<legacy_rel>`.
+/// Returns the path found along with whether it is the legacy location.
+///
+/// The legacy path takes precedence over the system-wide XDG paths, so a user's own legacy config is still found when an
+/// application also ships defaults in e.g. `/etc/xdg/<suffix>`. The order is the user-level XDG path, then the legacy
+/// path, then the system paths. If none of them exist, the [`Error::NotFound`] lists the legacy path after the XDG
+/// candidates.
+pub fn xdg_config_or_legacy(legacy_rel: &str, suffix: &str) -> Result<(PathBuf, bool), Error> {
+    if let Ok(user) = xdg_user_dir(&dirs::CONFIG, suffix)
+        && user.exists()
+    {
+        return Ok((user, false));
+    }
+
+    let legacy = XdgContext::new()
+        .home_dir()
+        .map(|home| home.join(legacy_rel));
+    if let Some(legacy) = legacy.as_ref().and_then(|p| p.canonicalize().ok())
+        && legacy.exists()
+    {
+        return Ok((legacy, true));
+    }
+
+    let err = match xdg_location_of(&dirs::CONFIG, suffix) {
+        Ok(path) => return Ok((path, false)),
+        Err(e) => e,
+    };
+
+    Err(match (err, legacy) {
+        (Error::NotFound(name, mut checked), Some(legacy)) => {
+            checked.push(legacy.to_string_lossy().to_string());
+            Error::NotFound(name, checked)
+        }
+        (e, _) => e,
+    })
+}
+
+/// Move the legacy `$HOME/<legacy_rel>` file or directory to the user-level XDG location of `suffix`, if the legacy path
+/// exists and the XDG one does not. An existing XDG path is never overwritten or removed, even if it is created
+/// concurrently.
+///
+/// The move is a rename where possible. Across filesystems it falls back to copying and then removing the legacy path,
+/// in which case a failed copy is cleaned up and leaves the legacy path untouched.
+pub fn xdg_migrate_legacy(
+    legacy_rel: &str,
+    xdg_dir: &XdgDir,
+    suffix: &str,
+) -> Result<MigrationResult, Error> {
+    let to = xdg_user_dir(xdg_dir, suffix)?;
+    if to.symlink_metadata().is_ok() {
+        return Ok(MigrationResult::AlreadyExists(to));
+    }
+
+    let from = XdgContext::new()
+        .home_dir()
+        .ok_or(Error::NoHome)?
+        .join(legacy_rel);
+    if from.symlink_metadata().is_err() {
+        return Ok(MigrationResult::NoLegacy);
+    }
+
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).map_err(io_error(parent))?;
+    }
+
+    // Reserve the XDG path with an empty placeholder of the same type, so that one created concurrently since the check
+    // above is never replaced by the rename or removed by the cleanup of a failed copy
+    let is_dir = from.symlink_metadata().map_err(io_error(&from))?.is_dir();
+    let reserved = if is_dir {
+        fs::create_dir(&to)
+    } else {
+        File::options()
+            .write(true)
+            .create_new(true)
+            .open(&to)
+            .map(drop)
+    };
+    match reserved {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+            return Ok(MigrationResult::AlreadyExists(to));
+        }
+        Err(e) => return Err(io_error(&to)(e)),
+    }
+
+    // Only attempt a rename when it can work, but still handle it failing for a filesystem boundary that was missed
+    let same_fs = to
+        .parent()
+        .is_none_or(|parent| same_filesystem(&from, parent).unwrap_or(true));
+    let renamed = same_fs
+        && match rename_over_placeholder(&from, &to, is_dir) {
+            Ok(()) => true,
+            Err(e) if e.kind() == io::ErrorKind::CrossesDevices => false,
+            Err(e) => {
+                let _ = remove_placeholder(&to, is_dir);
+                return Err(io_error(&from)(e));
+            }
+        };
+
+    if !renamed {
+        if let Err(e) = copy_into_placeholder(&from, &to) {
+            // Best-effort cleanup of the partial copy, which is entirely under the reserved path. The legacy path is
+            // still intact
+            let _ = remove_recursive(&to);
+            return Err(e);
+        }
+        remove_recursive(&from).map_err(io_error(&from))?;
+    }
+
+    Ok(MigrationResult::Moved { from, to })
+}
+
+/// Rename `from` over the empty placeholder at `to` created by [`xdg_migrate_legacy`].
+fn rename_over_placeholder(from: &Path, to: &Path, is_dir: bool) -> io::Result<()> {
+    // Renaming a directory over an empty one replaces it on Unix, but fails elsewhere
+    if is_dir && !cfg!(unix) {
+        fs::remove_dir(to)?;
+    }
+    fs::rename(from, to)
+}
+
+/// Remove the empty placeholder at `to` created by [`xdg_migrate_legacy`]. A directory is only removed if still empty.
+fn remove_placeholder(to: &Path, is_dir: bool) -> io::Result<()> {
+    if is_dir {
+        fs::remove_dir(to)
+    } else {
+        fs::remove_file(to)
+    }
+}
+
+/// Copy `from` to the empty placeholder at `to` created by [`xdg_migrate_legacy`], replacing it.
+fn copy_into_placeholder(from: &Path, to: &Path) -> Result<(), Error> {
+    let meta = from.symlink_metadata().map_err(io_error(from))?;
+
+    if meta.is_dir() {
+        fs::set_permissions(to, meta.permissions()).map_err(io_error(to))?;
+        for entry in fs::read_dir(from).map_err(io_error(from))? {
+            let entry = entry.map_err(io_error(from))?;
+            copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        if meta.is_symlink() {
+            fs::remove_file(to).map_err(io_error(to))?;
+        }
+        copy_recursive(from, to)
+    }
+}
+
+/// Copy a file, symlink, or directory tree from `from` to `to`, which must not exist.
+fn copy_recursive(from: &Path, to: &Path) -> Result<(), Error> {
+    let meta = from.symlink_metadata().map_err(io_error(from))?;
+
+    if meta.is_dir() {
+        fs::create_dir(to).map_err(io_error(to))?;
+        fs::set_permissions(to, meta.permissions()).map_err(io_error(to))?;
+        for entry in fs::read_dir(from).map_err(io_error(from))? {
+            let entry = entry.map_err(io_error(from))?;
+            copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+        }
+    } else if meta.is_symlink() {
+        let target = fs::read_link(from).map_err(io_error(from))?;
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(target, to).map_err(io_error(to))?;
+        #[cfg(not(unix))]
+        fs::copy(target, to).map_err(io_error(to))?;
+    } else {
+        fs::copy(from, to).map_err(io_error(to))?;
+    }
+
+    Ok(())
+}
+
+/// Remove a file, symlink, or directory tree.
+fn remove_recursive(path: &Path) -> io::Result<()> {
+    if path.symlink_metadata()?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
@@ -1,4 +1,116 @@
-use std::{env, path::PathBuf, str::FromStr};
+//! Helpers for locating files according to the [XDG Base Directory Specification](https://specifications.freedesktop.org/basedir-spec/latest).
+//!
+//! # Features
+//! - `fs` (default): functions that access the filesystem, e.g. [`xdg_location_of`]. Without it, only the pure path
+//!   computation functions such as [`xdg_user_dir`], [`xdg_system_dirs`], and [`xdg_candidate_paths`] are available.
+//! - `test-support`: fixtures for testing code that depends on the XDG environment.
+//! - `toml`, `json`: typed config loading from files in the given format.
+//! - `memmap2`: memory-mapping located files with `xdg_mmap`.
+//! - `cross-platform`: use the native locations of other platforms when the XDG variables are not set. On Windows, the
+//!   config dir defaults to `%APPDATA%`, the data and cache dirs to `%LOCALAPPDATA%`, and lists such as
+//!   `$XDG_DATA_DIRS` are `;`-separated. On macOS, the config and data dirs default to `~/Library/Application Support`
+//!   and the cache dir to `~/Library/Caches`. Without it, the XDG defaults are used on every platform.
+//! - `virtual-env`: reading variables from a map registered with `set_virtual_env` instead of the process environment,
+//!   e.g. for WASM hosts.
+//!
+//! # Suffixes
+//! Every path is resolved as a suffix relative to an XDG basedir, e.g. `app/settings.toml`. Leading, trailing, and
+//! repeated separators in the suffix are ignored. An empty suffix refers to the base directory itself, without a
+//! trailing separator: `xdg_user_dir(&dirs::CONFIG, "")` is `$XDG_CONFIG_HOME`, and `xdg_location_of(&dirs::CONFIG, "")`
+//! is the first of the config base directories that exists.
+//!
+//! # Thread safety
+//! All resolution functions only ever read the process environment, so they are safe to call concurrently from
+//! multiple threads, and all public types are `Send + Sync`. Note that *modifying* the environment (e.g. with
+//! [`std::env::set_var`]) while other threads resolve paths is not safe, as with any other environment access.
+//! To resolve against a different environment without modifying the process one (e.g. in tests), use
+//! [`XdgContext::with_env`] with an [`Env`] such as a `HashMap` or [`XdgEnv`].
+//!
+//! # Ordering
+//! All results are deterministic for a given environment and filesystem. Search paths and the locations found in them
+//! are in precedence order (user first, then system paths in order), and functions enumerating directory contents (e.g.
+//! [`xdg_walk`] and [`xdg_installed_app_ids`]) sort the entries by name.
+//!
+//! # Debugging
+//! Setting `XDG_DIRS_DEBUG=1` in the environment prints how every XDG basedir resolves (and where each path came from)
+//! to stderr the first time a path is resolved. It has no effect otherwise. The output is plain text unless stderr is a
+//! terminal and `NO_COLOR` is not set.
+
+use std::{
+    ffi::OsStr,
+    io,
+    path::{Component, Path, PathBuf},
+};
+
+#[cfg(feature = "fs")]
+mod atomic;
+#[cfg(any(feature = "toml", feature = "json"))]
+mod config;
+mod context;
+mod debug;
+mod diff;
+mod env;
+#[cfg(feature = "fs")]
+mod key_value;
+#[cfg(feature = "fs")]
+mod legacy;
+#[cfg(feature = "fs")]
+mod lock;
+#[cfg(feature = "memmap2")]
+mod mmap;
+mod namespace;
+mod prefix;
+mod resource;
+#[cfg(feature = "fs")]
+mod runtime;
+#[cfg(feature = "test-support")]
+mod test_support;
+#[cfg(feature = "fs")]
+mod usage;
+#[cfg(feature = "fs")]
+pub mod user_dirs;
+mod versioned;
+#[cfg(feature = "fs")]
+mod walk;
+
+#[cfg(feature = "fs")]
+pub use atomic::{same_filesystem, xdg_write_atomic, xdg_write_user_file};
+#[cfg(any(feature = "toml", feature = "json"))]
+pub use config::{Merge, xdg_load_config, xdg_load_layered};
+pub use context::XdgContext;
+pub use diff::{DiffReason, ResolutionDiff, diff_resolution};
+#[cfg(feature = "virtual-env")]
+pub use env::set_virtual_env;
+pub use env::{Env, ProcessEnv, XdgEnv};
+#[cfg(feature = "fs")]
+pub use key_value::{xdg_keyed_merge, xdg_locale_conf};
+#[cfg(feature = "fs")]
+pub use legacy::{MigrationResult, xdg_config_or_legacy, xdg_migrate_legacy};
+#[cfg(feature = "fs")]
+pub use lock::{InstanceGuard, xdg_single_instance_lock};
+#[cfg(feature = "memmap2")]
+pub use mmap::xdg_mmap;
+pub use namespace::{ConfigNamespace, config_namespace};
+pub use prefix::xdg_system_dirs_with_self;
+#[cfg(feature = "fs")]
+pub use resource::portal_config_file;
+pub use resource::{ResourceType, portal_config_dirs, xdg_resource_search_path};
+#[cfg(feature = "fs")]
+pub use runtime::{
+    Warning, dbus_session_address, wayland_socket, x11_socket, xdg_runtime_dir_checked,
+    xdg_runtime_dir_lenient,
+};
+#[cfg(all(feature = "fs", unix))]
+pub use runtime::{xdg_runtime_dir_or_default, xdg_runtime_private_dir};
+#[cfg(feature = "test-support")]
+pub use test_support::{XdgScaffold, scaffold_xdg_env};
+#[cfg(feature = "fs")]
+pub use usage::{AppUsage, xdg_app_disk_usage};
+#[cfg(feature = "fs")]
+pub use versioned::xdg_latest_config_version;
+pub use versioned::{xdg_profiled_config, xdg_versioned_config};
+#[cfg(feature = "fs")]
+pub use walk::{WalkEntry, xdg_walk};
 
 pub struct XdgDir {
     description: &'static str,
@@ -6,41 +118,195 @@ pub struct XdgDir {
     home_fallback: Option<&'static str>,
     system_var: Option<&'static str>,
     system_fallback: Option<&'static [&'static str]>,
+    prefix_subdir: Option<&'static str>,
+    platform_var: Option<&'static str>,
+}
+
+/// Environment-independent description of where files for an XDG basedir would be located.
+/// Paths are templates for display (e.g. in generated docs or `--help` text), not paths to resolve.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schema {
+    /// The user-level location in terms of the environment variable, e.g. `$XDG_CONFIG_HOME/app`
+    pub user_template: String,
+    /// The user-level location used when the environment variable is not set, e.g. `~/.config/app`
+    pub user_default: Option<String>,
+    /// The system-level locations in terms of the environment variable, e.g. `$XDG_CONFIG_DIRS/app`
+    pub system_template: Option<String>,
+    /// The system-level locations used when the environment variable is not set, e.g. `/etc/xdg/app`
+    pub system_defaults: Vec<String>,
+}
+
+impl XdgDir {
+    /// Define a custom basedir following XDG-like conventions, e.g. for an application-specific `MYAPP_CONFIG_DIRS`
+    /// overlay. It can be used with all the resolution functions the same way as the constants in [`dirs`].
+    ///
+    /// - `env_var`: the variable for the user-level location
+    /// - `home_fallback`: the user-level location relative to `$HOME` used if `env_var` is not set
+    /// - `system_var`: the variable listing the `:`-separated system-level locations
+    /// - `system_fallback`: the system-level locations used if `system_var` is not set
+    ///
+    /// ```
+    /// use xdg_dirs::XdgDir;
+    ///
+    /// const MYAPP_CONFIG: XdgDir = XdgDir::new(
+    ///     "MYAPP_CONFIG_HOME",
+    ///     Some(".myapp"),
+    ///     Some("MYAPP_CONFIG_DIRS"),
+    ///     Some(&["/etc/myapp"]),
+    /// )
+    /// .with_description("myapp configuration");
+    /// ```
+    pub const fn new(
+        env_var: &'static str,
+        home_fallback: Option<&'static str>,
+        system_var: Option<&'static str>,
+        system_fallback: Option<&'static [&'static str]>,
+    ) -> Self {
+        XdgDir {
+            description: env_var,
+            env_var,
+            home_fallback,
+            system_var,
+            system_fallback,
+            prefix_subdir: None,
+            platform_var: None,
+        }
+    }
+
+    /// Set the human-readable description used in errors such as [`Error::SystemDirNotApplicable`]. Defaults to the
+    /// name of the user-level environment variable.
+    pub const fn with_description(mut self, description: &'static str) -> Self {
+        self.description = description;
+        self
+    }
+
+    /// Set the subdirectory of an install prefix holding this basedir's files, e.g. `share` for `/usr/share`, as used by
+    /// [`xdg_system_dirs_with_self`].
+    pub const fn with_prefix_subdir(mut self, prefix_subdir: &'static str) -> Self {
+        self.prefix_subdir = Some(prefix_subdir);
+        self
+    }
+
+    /// The environment variable for the user-level location, e.g. `XDG_CONFIG_HOME`.
+    pub fn env_var(&self) -> &'static str {
+        self.env_var
+    }
+
+    /// The user-level location relative to `$HOME` used when [`XdgDir::env_var`] is not set, e.g. `.config`.
+    /// `None` if there is no default (i.e. for the runtime dir).
+    pub fn home_fallback(&self) -> Option<&'static str> {
+        self.home_fallback
+    }
+
+    /// The environment variable listing the system-level locations, e.g. `XDG_CONFIG_DIRS`.
+    /// `None` if the basedir has no system-level locations.
+    pub fn system_var(&self) -> Option<&'static str> {
+        self.system_var
+    }
+
+    /// The system-level locations used when [`XdgDir::system_var`] is not set, e.g. `["/etc/xdg"]`.
+    /// `None` if the basedir has no system-level locations.
+    pub fn system_fallback(&self) -> Option<&'static [&'static str]> {
+        self.system_fallback
+    }
+
+    /// The environment variable holding the platform's native user-level location, used before
+    /// [`XdgDir::home_fallback`] when [`XdgDir::env_var`] is not set, e.g. `APPDATA` for the config dir on Windows with
+    /// the `cross-platform` feature. `None` for the XDG defaults.
+    pub fn platform_var(&self) -> Option<&'static str> {
+        self.platform_var
+    }
+
+    /// Describe where files with the given suffix would go for this basedir, without reading the environment.
+    pub fn schema(&self, suffix: &str) -> Schema {
+        Schema {
+            user_template: join_template(&format!("${}", self.env_var), suffix),
+            user_default: self
+                .home_fallback
+                .map(|home_dir| join_template(&format!("~/{home_dir}"), suffix)),
+            system_template: self
+                .system_var
+                .map(|var| join_template(&format!("${var}"), suffix)),
+            system_defaults: self
+                .system_fallback
+                .unwrap_or_default()
+                .iter()
+                .map(|p| join_template(p, suffix))
+                .collect(),
+        }
+    }
+}
+
+/// Join a display template and suffix with exactly one separator between them. The suffix is normalized the same way as
+/// for resolved paths, dropping leading, trailing, repeated, and `.` separators.
+fn join_template(base: &str, suffix: &str) -> String {
+    let base = base.trim_end_matches('/');
+    let suffix = suffix
+        .split('/')
+        .filter(|c| !c.is_empty() && *c != ".")
+        .collect::<Vec<_>>()
+        .join("/");
+    if suffix.is_empty() {
+        base.to_string()
+    } else {
+        format!("{base}/{suffix}")
+    }
 }
 
 pub mod dirs {
     use super::XdgDir;
 
+    /// Whether the macOS conventions of the `cross-platform` feature apply.
+    const MACOS: bool = cfg!(all(feature = "cross-platform", target_os = "macos"));
+    /// Whether the Windows conventions of the `cross-platform` feature apply.
+    const WINDOWS: bool = cfg!(all(feature = "cross-platform", windows));
+
     pub const CONFIG: XdgDir = XdgDir {
         description: "configuration",
         env_var: "XDG_CONFIG_HOME",
-        home_fallback: Some(".config/"),
+        home_fallback: Some(if MACOS {
+            "Library/Application Support"
+        } else {
+            ".config"
+        }),
         system_var: Some("XDG_CONFIG_DIRS"),
         system_fallback: Some(&["/etc/xdg"]),
+        prefix_subdir: Some("etc/xdg"),
+        platform_var: if WINDOWS { Some("APPDATA") } else { None },
     };
 
     pub const DATA: XdgDir = XdgDir {
         description: "data",
         env_var: "XDG_DATA_HOME",
-        home_fallback: Some(".local/share/"),
+        home_fallback: Some(if MACOS {
+            "Library/Application Support"
+        } else {
+            ".local/share"
+        }),
         system_var: Some("XDG_DATA_DIRS"),
-        system_fallback: Some(&["/usr/local/share/", "/usr/share/"]),
+        system_fallback: Some(&["/usr/local/share", "/usr/share"]),
+        prefix_subdir: Some("share"),
+        platform_var: if WINDOWS { Some("LOCALAPPDATA") } else { None },
     };
 
     pub const CACHE: XdgDir = XdgDir {
         description: "cache",
         env_var: "XDG_CACHE_HOME",
-        home_fallback: Some(".cache/"),
+        home_fallback: Some(if MACOS { "Library/Caches" } else { ".cache" }),
         system_var: None,
         system_fallback: None,
+        prefix_subdir: None,
+        platform_var: if WINDOWS { Some("LOCALAPPDATA") } else { None },
     };
 
     pub const STATE: XdgDir = XdgDir {
         description: "state",
         env_var: "XDG_STATE_HOME",
-        home_fallback: Some(".local/state/"),
+        home_fallback: Some(".local/state"),
         system_var: None,
         system_fallback: None,
+        prefix_subdir: None,
+        platform_var: None,
     };
 
     pub const RUNTIME: XdgDir = XdgDir {
@@ -49,10 +315,26 @@ pub mod dirs {
         home_fallback: None,
         system_var: None,
         system_fallback: None,
+        prefix_subdir: None,
+        platform_var: None,
+    };
+
+    /// User executables, from the proposed `$XDG_BIN_HOME` addition to the spec.
+    pub const BIN: XdgDir = XdgDir {
+        description: "executables",
+        env_var: "XDG_BIN_HOME",
+        home_fallback: Some(".local/bin"),
+        system_var: None,
+        system_fallback: None,
+        prefix_subdir: None,
+        platform_var: None,
     };
+
+    /// All of the XDG base directories
+    pub const ALL: &[&XdgDir] = &[&CONFIG, &DATA, &CACHE, &STATE, &RUNTIME, &BIN];
 }
 
-#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
 #[non_exhaustive]
 pub enum Error {
     #[error("$HOME is not set")]
@@ -66,110 +348,1017 @@ pub enum Error {
 
     #[error("Path {0} not found in any of: {1:?}")]
     NotFound(String, Vec<String>),
+
+    /// Only the kind of the underlying [`io::Error`] is kept, so errors compare equal by path and kind
+    #[error("I/O error for {0:?}: {1}")]
+    Io(PathBuf, io::ErrorKind),
+
+    #[error("{1:?} is used as the base directory for ${0}, but is not a directory")]
+    BaseNotADirectory(&'static str, PathBuf),
+
+    #[error("Path {0:?} is longer than the platform limit of {PATH_MAX} bytes")]
+    PathTooLong(PathBuf),
+
+    #[error("Failed to parse config: {0:?}")]
+    Parse(Vec<(PathBuf, String)>),
+
+    #[error("Lock {0:?} is held by another instance")]
+    AlreadyLocked(PathBuf),
+
+    #[error("Runtime directory {0:?} is not owned by the current user with mode 0700")]
+    InsecureRuntimeDir(PathBuf),
+
+    #[error("{0:?} already exists but is not a directory")]
+    NotADirectory(PathBuf),
+
+    #[error("Permission denied checking for {0:?}")]
+    PermissionDenied(PathBuf),
+
+    #[error("Path {0:?} has a component longer than the platform limit of {NAME_MAX} bytes")]
+    NameTooLong(PathBuf),
+}
+
+/// The maximum length of a path on this platform, in bytes.
+#[cfg(unix)]
+pub const PATH_MAX: usize = libc::PATH_MAX as usize;
+/// The maximum length of a path on this platform, in bytes.
+#[cfg(windows)]
+pub const PATH_MAX: usize = 260;
+/// The maximum length of a path on this platform, in bytes.
+#[cfg(not(any(unix, windows)))]
+pub const PATH_MAX: usize = usize::MAX;
+
+/// The maximum length of a single path component (i.e. a file or directory name) on common filesystems, in bytes.
+pub const NAME_MAX: usize = 255;
+
+/// Optional check that an assembled path fits within [`PATH_MAX`], and each of its components within [`NAME_MAX`], to be
+/// chained onto the resolution functions, e.g. `xdg_user_dir(&dirs::DATA, suffix).and_then(check_path_max)`.
+/// This gives a clear [`Error::PathTooLong`] or [`Error::NameTooLong`] for long suffixes (e.g. built from user input)
+/// rather than an opaque OS error later. See [`XdgContext::with_length_check`] to apply it to every resolved path.
+pub fn check_path_max(path: PathBuf) -> Result<PathBuf, Error> {
+    // PATH_MAX includes the terminating null byte
+    if path.as_os_str().len() >= PATH_MAX {
+        return Err(Error::PathTooLong(path));
+    }
+    if path.iter().any(|c| c.len() > NAME_MAX) {
+        return Err(Error::NameTooLong(path));
+    }
+    Ok(path)
+}
+
+/// Convert an I/O error from operating on `path` into an [`Error::Io`].
+#[cfg(feature = "fs")]
+pub(crate) fn io_error(path: &Path) -> impl FnOnce(io::Error) -> Error {
+    move |e| Error::Io(path.to_path_buf(), e.kind())
 }
 
 /// Returns the user-path of a given XDG basedir, with the provided suffix, based on the relevant environment variables.
 /// This does NOT create the directory or check that it exists, and does not fall back to system-wide defaults if it is missing or user-level values are not set.
-pub fn xdg_user_dir(xdg_dir: &XdgDir, suffix: &str) -> Result<PathBuf, Error> {
-    let mut config_path = env::var(xdg_dir.env_var)
-        // Check the normal environment variable first
-        .map(|p| {
-            let Ok(path) = PathBuf::from_str(&*p);
-            path
-        })
-        // If not set, check the default value under $HOME (or return error if that doesn't apply)
-        .or_else(|_| match xdg_dir.home_fallback {
-            Some(home_dir) => env::var("HOME")
-                .map(|p| {
-                    let Ok(mut home_path) = PathBuf::from_str(&*p);
-                    home_path.push(home_dir);
-                    home_path
-                })
-                .map_err(|_| Error::NoHome),
-            None => Err(Error::EnvVarNotSet(xdg_dir.env_var)),
-        });
+/// An empty suffix gives the base directory itself.
+pub fn xdg_user_dir(xdg_dir: &XdgDir, suffix: impl AsRef<Path>) -> Result<PathBuf, Error> {
+    XdgContext::new().user_dir(xdg_dir, suffix)
+}
 
-    if let Ok(ref mut path) = config_path {
-        path.push(suffix);
-    }
+/// Returns the user-path of a given XDG basedir as with [`xdg_user_dir`], but with an arbitrary `OsStr` suffix.
+/// No part of the path is assumed to be UTF-8, including the values of the environment variables.
+pub fn xdg_user_dir_os(xdg_dir: &XdgDir, suffix: &OsStr) -> Result<PathBuf, Error> {
+    XdgContext::new().user_dir_os(xdg_dir, suffix)
+}
 
-    config_path
+/// Check that the user-level base directory of a given XDG basedir is usable, returning it if so.
+/// This catches e.g. `$XDG_CONFIG_HOME` mistakenly being set to a file, which would otherwise make every lookup under it
+/// silently fail with [`Error::NotFound`]. A base directory that does not exist yet is considered valid.
+#[cfg(feature = "fs")]
+pub fn xdg_validate_user_dir(xdg_dir: &XdgDir) -> Result<PathBuf, Error> {
+    let base = xdg_user_dir(xdg_dir, "")?;
+
+    match base.metadata() {
+        Ok(meta) if !meta.is_dir() => Err(Error::BaseNotADirectory(xdg_dir.env_var, base)),
+        _ => Ok(base),
+    }
 }
 
-pub fn xdg_config_dir(suffix: &str) -> Result<PathBuf, Error> {
+/// Returns the user config path for `suffix`, i.e. [`xdg_user_dir`] with `dirs::CONFIG` (`$XDG_CONFIG_HOME/<suffix>`).
+///
+/// ```
+/// if let Ok(path) = xdg_dirs::xdg_config_dir("my-app/file") {
+///     println!("{}", path.display());
+/// }
+/// ```
+pub fn xdg_config_dir(suffix: impl AsRef<Path>) -> Result<PathBuf, Error> {
     xdg_user_dir(&dirs::CONFIG, suffix)
 }
 
+/// Returns the user data path for `suffix`, i.e. [`xdg_user_dir`] with `dirs::DATA` (`$XDG_DATA_HOME/<suffix>`).
+///
+/// ```
+/// if let Ok(path) = xdg_dirs::xdg_data_dir("my-app/file") {
+///     println!("{}", path.display());
+/// }
+/// ```
+pub fn xdg_data_dir(suffix: impl AsRef<Path>) -> Result<PathBuf, Error> {
+    xdg_user_dir(&dirs::DATA, suffix)
+}
+
+/// Returns the user cache path for `suffix`, i.e. [`xdg_user_dir`] with `dirs::CACHE` (`$XDG_CACHE_HOME/<suffix>`).
+///
+/// ```
+/// if let Ok(path) = xdg_dirs::xdg_cache_dir("my-app/file") {
+///     println!("{}", path.display());
+/// }
+/// ```
+pub fn xdg_cache_dir(suffix: impl AsRef<Path>) -> Result<PathBuf, Error> {
+    xdg_user_dir(&dirs::CACHE, suffix)
+}
+
+/// Returns the user state path for `suffix`, i.e. [`xdg_user_dir`] with `dirs::STATE` (`$XDG_STATE_HOME/<suffix>`).
+///
+/// ```
+/// if let Ok(path) = xdg_dirs::xdg_state_dir("my-app/file") {
+///     println!("{}", path.display());
+/// }
+/// ```
+pub fn xdg_state_dir(suffix: impl AsRef<Path>) -> Result<PathBuf, Error> {
+    xdg_user_dir(&dirs::STATE, suffix)
+}
+
+/// Returns the user executables path for `suffix`, i.e. [`xdg_user_dir`] with `dirs::BIN` (`$XDG_BIN_HOME/<suffix>`).
+///
+/// ```
+/// if let Ok(path) = xdg_dirs::xdg_bin_dir("my-tool") {
+///     println!("{}", path.display());
+/// }
+/// ```
+pub fn xdg_bin_dir(suffix: impl AsRef<Path>) -> Result<PathBuf, Error> {
+    xdg_user_dir(&dirs::BIN, suffix)
+}
+
+/// Returns the user runtime path for `suffix`, i.e. [`xdg_user_dir`] with `dirs::RUNTIME` (`$XDG_RUNTIME_DIR/<suffix>`).
+/// There is no default for the runtime dir, so this returns [`Error::EnvVarNotSet`] if `$XDG_RUNTIME_DIR` is not set.
+///
+/// ```
+/// if let Ok(path) = xdg_dirs::xdg_runtime_dir("my-app/file") {
+///     println!("{}", path.display());
+/// }
+/// ```
+pub fn xdg_runtime_dir(suffix: impl AsRef<Path>) -> Result<PathBuf, Error> {
+    xdg_user_dir(&dirs::RUNTIME, suffix)
+}
+
 /// Returns the list of system paths for a given XDG basedir, with the provided suffix, based on the relevant environment variable.
 /// This does NOT create the directories or check that they exist, only returns the list of candidates.
-pub fn xdg_system_dirs(xdg_dir: &XdgDir, suffix: &str) -> Result<Vec<PathBuf>, Error> {
-    // Parse the env var, if it is set
-    // Note: this follows the same format as PATH, which does not allow for any escaping or quoting of ':' in path names
-    if let Some(var) = xdg_dir.system_var {
-        if let Ok(val) = env::var(var) {
-            if !val.is_empty() {
-                return Ok(val
-                    .split(':')
-                    .map(|p| {
-                        let Ok(mut path) = PathBuf::from_str(p);
-                        path.push(suffix);
-                        path
-                    })
-                    .collect());
+/// Entries repeated in the environment variable are only included once, in the position of their first occurrence.
+pub fn xdg_system_dirs(xdg_dir: &XdgDir, suffix: impl AsRef<Path>) -> Result<Vec<PathBuf>, Error> {
+    XdgContext::new().system_dirs(xdg_dir, suffix)
+}
+
+/// Where a path returned by [`xdg_system_dirs_tagged`] came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SysDirSource {
+    /// From the basedir's environment variable, e.g. `$XDG_DATA_DIRS`
+    EnvVar,
+    /// From the default used when the environment variable is not set, e.g. `/usr/local/share:/usr/share`
+    Fallback,
+    /// From the `/etc/<suffix>` fallback enabled with [`XdgContext::with_etc_fallback`]
+    Etc,
+}
+
+/// Returns the list of system paths for a given XDG basedir as with [`xdg_system_dirs`], along with whether each came
+/// from the environment variable or the default fallback.
+pub fn xdg_system_dirs_tagged(
+    xdg_dir: &XdgDir,
+    suffix: impl AsRef<Path>,
+) -> Result<Vec<(PathBuf, SysDirSource)>, Error> {
+    XdgContext::new().system_dirs_tagged(xdg_dir, suffix)
+}
+
+/// Returns every candidate path for a given XDG basedir in the order they are searched by [`xdg_location_of`]: the user
+/// path (if applicable) followed by the system paths (if applicable), skipping any that refer to the same location.
+/// This does NOT check that any of them exist.
+pub fn xdg_candidate_paths(xdg_dir: &XdgDir, suffix: impl AsRef<Path>) -> Vec<PathBuf> {
+    XdgContext::new().candidate_paths(xdg_dir, suffix)
+}
+
+/// Lazily yield every candidate path for a given XDG basedir, in the same order and with the same de-duplication as
+/// [`xdg_candidate_paths`], e.g. to stop at the first match without computing the rest:
+///
+/// ```
+/// use xdg_dirs::{dirs, xdg_iter};
+///
+/// let config = xdg_iter(&dirs::CONFIG, "app.toml").find(|p| p.is_file());
+/// ```
+///
+/// The system paths are only computed if the iterator gets past the user path. A basedir without a user path (e.g. if
+/// `$XDG_RUNTIME_DIR` is not set) or without system paths simply yields fewer items.
+pub fn xdg_iter<'a>(xdg_dir: &'a XdgDir, suffix: &'a str) -> XdgCandidates<'a> {
+    XdgCandidates {
+        ctx: XdgContext::new(),
+        xdg_dir,
+        suffix,
+        user_done: false,
+        system: None,
+        seen: std::collections::HashSet::new(),
+    }
+}
+
+/// Iterator over the candidate paths for a given XDG basedir, as returned by [`xdg_iter`].
+pub struct XdgCandidates<'a> {
+    ctx: XdgContext,
+    xdg_dir: &'a XdgDir,
+    suffix: &'a str,
+    /// Whether the user path has been yielded (or skipped)
+    user_done: bool,
+    /// The remaining system paths, once they have been computed
+    system: Option<std::vec::IntoIter<PathBuf>>,
+    /// Keys of the locations yielded so far, see [`context::dedup_key`]
+    seen: std::collections::HashSet<PathBuf>,
+}
+
+impl Iterator for XdgCandidates<'_> {
+    type Item = PathBuf;
+
+    fn next(&mut self) -> Option<PathBuf> {
+        if !self.user_done {
+            self.user_done = true;
+            if let Ok(path) = self.ctx.user_dir(self.xdg_dir, self.suffix) {
+                self.seen.insert(context::dedup_key(&path));
+                return Some(path);
             }
         }
+
+        let (ctx, xdg_dir, suffix) = (&self.ctx, self.xdg_dir, self.suffix);
+        let system = self.system.get_or_insert_with(|| {
+            ctx.system_dirs(xdg_dir, suffix)
+                .unwrap_or_default()
+                .into_iter()
+        });
+        system.find(|path| self.seen.insert(context::dedup_key(path)))
     }
+}
 
-    // If the env var is not set, fall back to the default
-    if let Some(paths) = xdg_dir.system_fallback {
-        return Ok(paths
-            .iter()
-            .map(|p| {
-                let Ok(mut path) = PathBuf::from_str(p);
-                path.push(suffix);
-                path
-            })
-            .collect());
+/// The candidate paths for a given XDG basedir, split by level, as returned by [`xdg_candidates`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidates {
+    /// The user-level path as from [`xdg_user_dir`], which is where files should be written. `None` if it is not
+    /// available (e.g. `$XDG_RUNTIME_DIR` is not set)
+    pub user: Option<PathBuf>,
+    /// The system-level paths as from [`xdg_system_dirs`] in precedence order, which are only read from. Empty if the
+    /// basedir has none
+    pub system: Vec<PathBuf>,
+}
+
+/// Returns the candidate paths for a given XDG basedir as with [`xdg_candidate_paths`], but keeping the user-level path
+/// separate from the system-level ones, e.g. to write to the former while reading from any of them.
+/// This does NOT check that any of them exist.
+///
+/// Returns the error from [`xdg_user_dir`] if there are no candidates at all.
+pub fn xdg_candidates(xdg_dir: &XdgDir, suffix: impl AsRef<Path>) -> Result<Candidates, Error> {
+    let suffix = suffix.as_ref();
+    let user = xdg_user_dir(xdg_dir, suffix);
+    let system = xdg_system_dirs(xdg_dir, suffix).unwrap_or_default();
+
+    match user {
+        Err(e) if system.is_empty() => Err(e),
+        user => Ok(Candidates {
+            user: user.ok(),
+            system,
+        }),
     }
+}
 
-    // Otherwise, there's either nothing set or there is not intended to be a system-level fallback
-    Err(match xdg_dir.system_var {
-        Some(var) => Error::EnvVarNotSet(var),
-        None => Error::SystemDirNotApplicable(xdg_dir.description),
-    })
+/// Create the user-level directory `suffix` for a given XDG basedir (e.g. `$XDG_CONFIG_HOME/<suffix>`), along with any
+/// missing parent directories. Returns the path of the directory. Succeeds if it already exists, and returns
+/// [`Error::NotADirectory`] if something else (e.g. a regular file) is already at that path.
+///
+/// The directory permissions come from the process umask, see [`XdgContext::secure_mode`] to restrict them.
+#[cfg(feature = "fs")]
+pub fn xdg_create_dir(xdg_dir: &XdgDir, suffix: &str) -> Result<PathBuf, Error> {
+    XdgContext::new().create_dir(xdg_dir, suffix)
+}
+
+/// Create the user-level directory `suffix` for a given XDG basedir along with any missing parents, as with
+/// [`xdg_create_dir`], and return its path. For `dirs::RUNTIME`, the directories created are given mode `0700` on Unix
+/// regardless of the umask, as the spec requires the runtime dir to be private. Existing directories are not changed.
+#[cfg(feature = "fs")]
+pub fn xdg_create_user_dir(xdg_dir: &XdgDir, suffix: &str) -> Result<PathBuf, Error> {
+    let ctx = XdgContext::new();
+    if xdg_dir.env_var == dirs::RUNTIME.env_var {
+        ctx.secure_mode().create_dir(xdg_dir, suffix)
+    } else {
+        ctx.create_dir(xdg_dir, suffix)
+    }
+}
+
+/// Write `contents` to the user-level file `suffix` for a given XDG basedir (e.g. `$XDG_CONFIG_HOME/<suffix>`), creating
+/// any missing parent directories and replacing the file if it exists. Returns the path of the file.
+///
+/// The permissions come from the process umask, see [`XdgContext::secure_mode`] to restrict them.
+#[cfg(feature = "fs")]
+pub fn xdg_write(
+    xdg_dir: &XdgDir,
+    suffix: &str,
+    contents: impl AsRef<[u8]>,
+) -> Result<PathBuf, Error> {
+    XdgContext::new().write(xdg_dir, suffix, contents)
+}
+
+/// Make sure the user config file `suffix` (e.g. `app/config.toml`) exists, for first-run setup: the missing parent
+/// directories are created and the file is written with `default` if it does not exist. An existing file is never
+/// overwritten. Returns the path of the file either way.
+#[cfg(feature = "fs")]
+pub fn xdg_ensure_config_file(suffix: &str, default: &[u8]) -> Result<PathBuf, Error> {
+    use std::io::Write;
+
+    let path = xdg_config_dir(suffix)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(io_error(parent))?;
+    }
+
+    // Only create the file if it does not exist yet, without a separate check that would be racy
+    match std::fs::File::options()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+    {
+        Ok(mut file) => {
+            if let Err(e) = file.write_all(default) {
+                // Remove the partial file, otherwise it would be kept as the config from then on
+                drop(file);
+                let _ = std::fs::remove_file(&path);
+                return Err(io_error(&path)(e));
+            }
+        }
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+        Err(e) => return Err(io_error(&path)(e)),
+    }
+
+    Ok(path)
+}
+
+/// The maximum number of bytes of each file passed to the predicate of [`xdg_find_by_content`].
+#[cfg(feature = "fs")]
+pub const CONTENT_PREFIX_LEN: usize = 4096;
+
+/// Find the first file directly under `subdir` in the candidate locations of the given XDG basedir whose contents match
+/// `predicate`, e.g. to auto-detect config files starting with `#!myapp`. Candidate directories are searched in
+/// precedence order, and the files within each in order of name.
+///
+/// Only the first [`CONTENT_PREFIX_LEN`] bytes (or fewer for a shorter file) are read and passed to `predicate`, to
+/// avoid loading huge files. Files that cannot be read are skipped.
+#[cfg(feature = "fs")]
+pub fn xdg_find_by_content(
+    xdg_dir: &XdgDir,
+    subdir: &str,
+    predicate: impl Fn(&[u8]) -> bool,
+) -> Option<PathBuf> {
+    use std::io::Read;
+
+    let read_prefix = |path: &Path| -> io::Result<Vec<u8>> {
+        let mut prefix = Vec::with_capacity(CONTENT_PREFIX_LEN);
+        std::fs::File::open(path)?
+            .take(CONTENT_PREFIX_LEN as u64)
+            .read_to_end(&mut prefix)?;
+        Ok(prefix)
+    };
+
+    xdg_candidate_paths(xdg_dir, subdir)
+        .into_iter()
+        .find_map(|dir| {
+            let mut files: Vec<_> = std::fs::read_dir(dir)
+                .ok()?
+                .filter_map(|entry| Some(entry.ok()?.path()))
+                .filter(|path| path.is_file())
+                .collect();
+            files.sort();
+
+            files
+                .into_iter()
+                .find(|path| read_prefix(path).is_ok_and(|prefix| predicate(&prefix)))
+        })
+}
+
+/// Returns the full search path for `suffix` in the exact order [`xdg_location_of`] checks it, as with
+/// [`xdg_candidate_paths`], e.g. to log everywhere a missing file was looked for.
+/// Unlike [`xdg_candidate_paths`], this returns the error from resolving the user path if there are no paths at all
+/// (e.g. [`Error::EnvVarNotSet`] for the runtime dir when `$XDG_RUNTIME_DIR` is not set).
+pub fn xdg_search_paths(xdg_dir: &XdgDir, suffix: &str) -> Result<Vec<PathBuf>, Error> {
+    let paths = xdg_candidate_paths(xdg_dir, suffix);
+    if paths.is_empty() {
+        xdg_user_dir(xdg_dir, suffix)?;
+    }
+
+    Ok(paths)
+}
+
+/// Call `f` on each candidate path from [`xdg_candidate_paths`] in precedence order, and return the first `Some` it
+/// returns, e.g. to find the first candidate that can be opened and parsed. Returns `None` if `f` does for every candidate.
+pub fn xdg_try_each<T>(
+    xdg_dir: &XdgDir,
+    suffix: &str,
+    mut f: impl FnMut(&Path) -> Option<T>,
+) -> Option<T> {
+    xdg_candidate_paths(xdg_dir, suffix)
+        .iter()
+        .find_map(|p| f(p))
+}
+
+/// Whether the user cache file `suffix` (i.e. `$XDG_CACHE_HOME/<suffix>`) exists and was modified at most `max_age` ago.
+/// A modification time in the future counts as fresh.
+#[cfg(feature = "fs")]
+pub fn xdg_cache_fresh(suffix: &str, max_age: std::time::Duration) -> bool {
+    XdgContext::new().cache_fresh(suffix, max_age)
+}
+
+/// Returns every existing candidate location of `suffix` for a given XDG basedir along with how long ago it was modified,
+/// with the most recently modified first. Locations modified at the same time are kept in precedence order.
+#[cfg(feature = "fs")]
+pub fn xdg_locations_by_mtime(
+    xdg_dir: &XdgDir,
+    suffix: &str,
+) -> Vec<(PathBuf, std::time::Duration)> {
+    XdgContext::new().locations_by_mtime(xdg_dir, suffix)
 }
 
 /// Search all relevant paths for the given XDG base directory and find the first one where `suffix` exists.
 /// This follows the precedence of searching the path in the user's HOME first, and then system fallbacks (if applicable) in order.
 ///
 /// Notes:
-///  - This only checks that the path exists and is accessible, not type (file vs directory) or exact permissions on the file/directory'
+///  - This only checks that the path exists and is accessible, not type (file vs directory) or exact permissions on the file/directory' (see [`xdg_location_of_kind`] to require a type)
 ///  - Beware of TOCTOU issues
-pub fn xdg_location_of(xdg_dir: &XdgDir, suffix: &str) -> Result<PathBuf, Error> {
-    let mut checked = vec![];
+///  - A candidate that cannot be checked for lack of permission (e.g. in a directory that is not searchable) is skipped.
+///    If nothing is found, this returns [`Error::PermissionDenied`] for the first such candidate rather than
+///    [`Error::NotFound`]
+///  - With an empty suffix, this finds the first base directory that exists
+#[cfg(feature = "fs")]
+pub fn xdg_location_of(xdg_dir: &XdgDir, suffix: impl AsRef<Path>) -> Result<PathBuf, Error> {
+    XdgContext::new().location_of(xdg_dir, suffix)
+}
 
-    // Check user location
-    if let Ok(user_loc) = xdg_user_dir(xdg_dir, suffix) {
-        checked.push(user_loc.to_string_lossy().to_string());
-        if let Ok(user_loc) = user_loc.canonicalize() {
-            if user_loc.exists() {
-                return Ok(user_loc);
+/// Search all relevant paths for the given XDG base directory as with [`xdg_location_of`], but return the path as found
+/// rather than canonicalizing it. Symlinks in the path (e.g. a config directory linked into a dotfiles repository) are
+/// kept, so writes relative to it land where the user expects.
+///
+/// Symlinks are still followed to check that the target exists, so a dangling symlink is skipped.
+#[cfg(feature = "fs")]
+pub fn xdg_location_of_no_follow(
+    xdg_dir: &XdgDir,
+    suffix: impl AsRef<Path>,
+) -> Result<PathBuf, Error> {
+    xdg_location_of_with(xdg_dir, suffix, |p| p.try_exists().unwrap_or(false))
+}
+
+/// Returns every existing location of `suffix` for the given XDG basedir in precedence order (user first, then each
+/// system path), canonicalized as with [`xdg_location_of`] and skipping any that refer to the same location, e.g. to
+/// read and layer every copy of a config file.
+///
+/// Returns `Ok(vec![])` if none exist, and only fails if there are no paths to search (see [`xdg_search_paths`]).
+#[cfg(feature = "fs")]
+pub fn xdg_all_locations_of(xdg_dir: &XdgDir, suffix: &str) -> Result<Vec<PathBuf>, Error> {
+    let mut seen = std::collections::HashSet::new();
+
+    Ok(xdg_search_paths(xdg_dir, suffix)?
+        .into_iter()
+        .filter_map(|p| p.canonicalize().ok())
+        .filter(|p| seen.insert(p.clone()))
+        .collect())
+}
+
+/// Fold every existing copy of the config file `suffix` into one value, from lowest to highest precedence: `f` is called
+/// with each file found by [`xdg_all_locations_of`] in reverse order (the last system directory first, the user's file
+/// last), so values from the user's file can override the system defaults. Directories with the same name are skipped.
+///
+/// Returns `init` if no file exists, and only fails if there are no paths to search (see [`xdg_search_paths`]).
+#[cfg(feature = "fs")]
+pub fn xdg_merge_config<T>(
+    suffix: &str,
+    init: T,
+    f: impl FnMut(T, PathBuf) -> T,
+) -> Result<T, Error> {
+    Ok(xdg_all_locations_of(&dirs::CONFIG, suffix)?
+        .into_iter()
+        .rev()
+        .filter(|p| p.is_file())
+        .fold(init, f))
+}
+
+/// What is at a candidate path, as returned by [`xdg_candidate_status`]. The flags describe the entry itself without
+/// following symlinks, so a symlink to a directory has `is_symlink` set but not `is_dir`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CandidateStatus {
+    /// The candidate path
+    pub path: PathBuf,
+    /// Whether anything is at the path, including a dangling symlink
+    pub exists: bool,
+    /// Whether the entry is a directory
+    pub is_dir: bool,
+    /// Whether the entry is a regular file
+    pub is_file: bool,
+    /// Whether the entry is a symlink
+    pub is_symlink: bool,
+}
+
+/// Returns every candidate path for a given XDG basedir as with [`xdg_candidate_paths`], along with what is at each
+/// according to a single `symlink_metadata` call, e.g. to render a detailed table of the search path for debugging.
+/// A candidate that cannot be checked (e.g. for lack of permission) is reported as not existing.
+#[cfg(feature = "fs")]
+pub fn xdg_candidate_status(xdg_dir: &XdgDir, suffix: impl AsRef<Path>) -> Vec<CandidateStatus> {
+    xdg_candidate_paths(xdg_dir, suffix)
+        .into_iter()
+        .map(|path| {
+            let file_type = path.symlink_metadata().ok().map(|meta| meta.file_type());
+            CandidateStatus {
+                exists: file_type.is_some(),
+                is_dir: file_type.is_some_and(|t| t.is_dir()),
+                is_file: file_type.is_some_and(|t| t.is_file()),
+                is_symlink: file_type.is_some_and(|t| t.is_symlink()),
+                path,
             }
+        })
+        .collect()
+}
+
+/// Which kinds of filesystem entry [`xdg_location_of_kind`] accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    /// Anything that exists, as with [`xdg_location_of`]
+    Any,
+    /// A regular file, or a symlink to one
+    File,
+    /// A directory, or a symlink to one
+    Dir,
+}
+
+/// Search all relevant paths for the given XDG base directory as with [`xdg_location_of`], but skip entries that are not
+/// of the given `kind` and continue the search. For example, with [`EntryKind::File`] a directory with the same name as a
+/// config file in a higher precedence location does not shadow the config file.
+///
+/// As with [`xdg_location_of`], this returns [`Error::PermissionDenied`] rather than [`Error::NotFound`] if nothing is
+/// found but a candidate could not be checked for lack of permission.
+#[cfg(feature = "fs")]
+pub fn xdg_location_of_kind(
+    xdg_dir: &XdgDir,
+    suffix: &str,
+    kind: EntryKind,
+) -> Result<PathBuf, Error> {
+    XdgContext::new().location_of_matching(xdg_dir, suffix, |meta| match kind {
+        EntryKind::Any => true,
+        EntryKind::File => meta.is_file(),
+        EntryKind::Dir => meta.is_dir(),
+    })
+}
+
+/// Search all relevant paths for the given XDG base directory as with [`xdg_location_of`], but using `exists` to decide
+/// whether a candidate exists rather than the filesystem, e.g. for config stored in an archive or other virtual filesystem.
+/// The returned path is not canonicalized.
+pub fn xdg_location_of_with(
+    xdg_dir: &XdgDir,
+    suffix: impl AsRef<Path>,
+    exists: impl Fn(&Path) -> bool,
+) -> Result<PathBuf, Error> {
+    XdgContext::new().location_of_with(xdg_dir, suffix, exists)
+}
+
+/// Search all relevant paths for the given XDG base directory as with [`xdg_location_of`], and then `suffix` under each of
+/// the `extra` directories in order (e.g. plugin directories configured by the user). If it is not found anywhere, the
+/// [`Error::NotFound`] lists the extra locations after the XDG ones.
+#[cfg(feature = "fs")]
+pub fn xdg_location_of_with_extra(
+    xdg_dir: &XdgDir,
+    suffix: &str,
+    extra: &[PathBuf],
+) -> Result<PathBuf, Error> {
+    let mut checked = vec![];
+
+    let candidates = xdg_candidate_paths(xdg_dir, suffix)
+        .into_iter()
+        .chain(extra.iter().map(|dir| {
+            let mut path = dir.clone();
+            context::push_suffix(&mut path, suffix);
+            path
+        }));
+    for p in candidates {
+        checked.push(p.to_string_lossy().to_string());
+        if p.try_exists().unwrap_or(false) {
+            return Ok(p.canonicalize().unwrap_or(p));
         }
     }
 
-    // Check system locations if not present in any user location
-    if let Ok(sys_paths) = xdg_system_dirs(xdg_dir, suffix) {
-        for p in sys_paths {
-            checked.push(p.to_string_lossy().to_string());
-            if let Ok(p) = p.canonicalize() {
-                if p.exists() {
-                    return Ok(p);
-                }
+    Err(Error::NotFound(suffix.to_string(), checked))
+}
+
+/// Search for each of `suffixes` in turn as with [`xdg_location_of`], returning the first one found. The whole search
+/// path is exhausted for a suffix before moving on to the next, so e.g. with `["app/local.toml", "app.toml"]` a system
+/// `app/local.toml` takes precedence over a user `app.toml`. This differs from trying several names within each
+/// directory, where the user directory is checked for every name before any system directory.
+///
+/// If none are found, the [`Error::NotFound`] names all the suffixes and lists every location checked, or if a candidate
+/// could not be checked for lack of permission, this returns [`Error::PermissionDenied`] for the first such candidate.
+#[cfg(feature = "fs")]
+pub fn xdg_locate_suffix_chain(xdg_dir: &XdgDir, suffixes: &[&str]) -> Result<PathBuf, Error> {
+    let mut checked = vec![];
+    let mut denied = None;
+
+    for suffix in suffixes {
+        match xdg_location_of(xdg_dir, suffix) {
+            Err(Error::NotFound(_, paths)) => checked.extend(paths),
+            // Keep looking, a later suffix may still be found
+            Err(Error::PermissionDenied(path)) => {
+                denied.get_or_insert(path);
             }
+            res => return res,
+        }
+    }
+
+    match denied {
+        Some(path) => Err(Error::PermissionDenied(path)),
+        None => Err(Error::NotFound(suffixes.join(", "), checked)),
+    }
+}
+
+/// Where to get a config file from, as returned by [`xdg_config_source`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    /// The config file was found at this path
+    OnDisk(PathBuf),
+    /// The config file was not found, so the default embedded in the application should be used
+    Embedded(&'static [u8]),
+}
+
+/// Find a config file as with [`xdg_location_of`] for `dirs::CONFIG`, or fall back to `embedded_default` (e.g. from
+/// `include_bytes!`) if it does not exist anywhere.
+#[cfg(feature = "fs")]
+pub fn xdg_config_source(suffix: &str, embedded_default: &'static [u8]) -> ConfigSource {
+    match xdg_location_of(&dirs::CONFIG, suffix) {
+        Ok(path) => ConfigSource::OnDisk(path),
+        Err(_) => ConfigSource::Embedded(embedded_default),
+    }
+}
+
+/// Which candidate location a path was found in by [`xdg_locate_full`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    /// The user-level location, e.g. under `$XDG_CONFIG_HOME`
+    User,
+    /// One of the system locations, e.g. from `$XDG_CONFIG_DIRS`
+    System(SysDirSource),
+}
+
+/// The full result of searching for a path with [`xdg_locate_full`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Located {
+    /// The first existing location, canonicalized as with [`xdg_location_of`], or `None` if it does not exist anywhere
+    pub found: Option<PathBuf>,
+    /// Every candidate that was checked in order, including the one that was found
+    pub searched: Vec<PathBuf>,
+    /// Where the found path came from, or `None` if it was not found
+    pub origin: Option<Origin>,
+}
+
+impl Located {
+    /// The found path along with where it came from, or `None` if it was not found.
+    pub fn resolved(&self) -> Option<ResolvedDir> {
+        Some(ResolvedDir {
+            path: self.found.clone()?,
+            origin: self.origin?,
+        })
+    }
+}
+
+/// A path found by a search along with where it came from, usable directly wherever a path is expected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedDir {
+    /// The path that was found
+    pub path: PathBuf,
+    /// Which candidate location it was found in
+    pub origin: Origin,
+}
+
+impl AsRef<Path> for ResolvedDir {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl std::ops::Deref for ResolvedDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Search for `suffix` as with [`xdg_location_of`], but return the candidates that were searched and where the result
+/// came from whether or not it was found.
+#[cfg(feature = "fs")]
+pub fn xdg_locate_full(xdg_dir: &XdgDir, suffix: &str) -> Located {
+    let ctx = XdgContext::new();
+    let candidates = ctx
+        .user_dir(xdg_dir, suffix)
+        .into_iter()
+        .map(|p| (p, Origin::User))
+        .chain(
+            ctx.system_dirs_tagged(xdg_dir, suffix)
+                .into_iter()
+                .flatten()
+                .map(|(p, source)| (p, Origin::System(source))),
+        );
+
+    let mut seen = std::collections::HashSet::new();
+    let mut searched = vec![];
+    for (p, origin) in candidates.filter(|(p, _)| seen.insert(context::dedup_key(p))) {
+        searched.push(p.clone());
+        if p.try_exists().unwrap_or(false) {
+            return Located {
+                found: Some(p.canonicalize().unwrap_or(p)),
+                searched,
+                origin: Some(origin),
+            };
+        }
+    }
+
+    Located {
+        found: None,
+        searched,
+        origin: None,
+    }
+}
+
+/// Returns both where to read a file from and where to write it to, for the given XDG basedir.
+/// The read path is the first existing location as found by [`xdg_location_of`] (or `None` if it does not exist anywhere),
+/// and the write path is always the user-level location from [`xdg_user_dir`].
+#[cfg(feature = "fs")]
+pub fn xdg_read_write_paths(
+    xdg_dir: &XdgDir,
+    suffix: &str,
+) -> Result<(Option<PathBuf>, PathBuf), Error> {
+    let write = xdg_user_dir(xdg_dir, suffix)?;
+    let read = xdg_location_of(xdg_dir, suffix).ok();
+
+    Ok((read, write))
+}
+
+/// Find every XDG basedir where `suffix` already exists, along with the location it was found at in each.
+/// This is intended as a diagnostic when choosing file layouts, to avoid names that are already used in a different basedir.
+#[cfg(feature = "fs")]
+pub fn suffix_collisions(suffix: &str) -> Vec<(&'static XdgDir, PathBuf)> {
+    dirs::ALL
+        .iter()
+        .filter_map(|&xdg_dir| {
+            xdg_location_of(xdg_dir, suffix)
+                .ok()
+                .map(|path| (xdg_dir, path))
+        })
+        .collect()
+}
+
+/// Find every location where `suffix` exists in every XDG basedir, tagged with the basedir it was found in, e.g. to
+/// detect files written to the wrong basedir. Unlike [`suffix_collisions`], this includes all existing candidates of
+/// each basedir (as with [`xdg_existing_candidates`]) rather than only the first.
+#[cfg(feature = "fs")]
+pub fn xdg_find_in_all_bases(suffix: &str) -> Vec<(&'static XdgDir, PathBuf)> {
+    dirs::ALL
+        .iter()
+        .flat_map(|&xdg_dir| {
+            xdg_existing_candidates(xdg_dir, suffix).map(move |path| (xdg_dir, path))
+        })
+        .collect()
+}
+
+/// Find the location of `suffix` as with [`xdg_location_of`], and return it as a percent-encoded `file://` URI.
+/// All bytes except unreserved characters (RFC 3986) and the `/` separator are encoded, including non-ASCII characters.
+#[cfg(feature = "fs")]
+pub fn xdg_location_uri(xdg_dir: &XdgDir, suffix: &str) -> Result<String, Error> {
+    let path = xdg_location_of(xdg_dir, suffix)?;
+
+    Ok(format!(
+        "file://{}",
+        percent_encode(&path, |b| b"-._~/".contains(&b))
+    ))
+}
+
+/// Percent-encode the bytes of `path`, except ASCII alphanumerics and any other bytes accepted by `keep`.
+#[cfg(feature = "fs")]
+pub(crate) fn percent_encode(path: &Path, keep: impl Fn(u8) -> bool) -> String {
+    #[cfg(unix)]
+    let bytes = std::os::unix::ffi::OsStrExt::as_bytes(path.as_os_str()).to_vec();
+    #[cfg(not(unix))]
+    let bytes = path.to_string_lossy().replace('\\', "/").into_bytes();
+
+    let mut encoded = String::new();
+    for b in bytes {
+        if b.is_ascii_alphanumeric() || keep(b) {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{b:02X}"));
+        }
+    }
+
+    encoded
+}
+
+/// Returns where to write a user override of a system-provided resource (e.g. an icon theme under `dirs::DATA`).
+/// This is the user-level path from [`xdg_user_dir`], which takes precedence over every path from [`xdg_system_dirs`].
+/// Returns [`Error::SystemDirNotApplicable`] for basedirs that have no system-level paths to override.
+pub fn xdg_user_override_path(xdg_dir: &XdgDir, suffix: &str) -> Result<PathBuf, Error> {
+    if xdg_dir.system_var.is_none() && xdg_dir.system_fallback.is_none() {
+        return Err(Error::SystemDirNotApplicable(xdg_dir.description));
+    }
+
+    xdg_user_dir(xdg_dir, suffix)
+}
+
+/// Returns the single path where an override of the layered file `suffix` should be written, for files that are read by
+/// merging every copy across the search path. Reads should go through [`xdg_all_locations_of`] (or
+/// [`xdg_keyed_merge`] for `KEY=VALUE` files), and writes only ever go to this path, leaving the system defaults intact.
+/// Since the user-level location has the highest precedence, the override wins over every system layer when merged.
+///
+/// This is the same path as [`xdg_user_override_path`], and fails in the same way for basedirs with no system layers.
+pub fn xdg_override_target(xdg_dir: &XdgDir, suffix: &str) -> Result<PathBuf, Error> {
+    xdg_user_override_path(xdg_dir, suffix)
+}
+
+/// Compute the relative path from the user-level directory `from_suffix` of `from` to the user-level path `to_suffix` of
+/// `to`, e.g. `../../.local/share/app/data` from `~/.config/app` to `~/.local/share/app/data` with the default locations.
+///
+/// The `from` path is treated as a directory, and the computation is purely lexical (symlinks are not resolved).
+/// This only makes sense when both resolve under the same root, e.g. when neither XDG variable is set and both are under `$HOME`.
+pub fn relative_between(
+    from: &XdgDir,
+    from_suffix: &str,
+    to: &XdgDir,
+    to_suffix: &str,
+) -> Result<PathBuf, Error> {
+    let from = xdg_user_dir(from, from_suffix)?;
+    let to = xdg_user_dir(to, to_suffix)?;
+
+    let from: Vec<_> = from.components().collect();
+    let to: Vec<_> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+
+    Ok(
+        std::iter::repeat_n(Component::ParentDir, from.len() - common)
+            .chain(to[common..].iter().copied())
+            .collect(),
+    )
+}
+
+/// Lazily yield each candidate location of `suffix` that exists, in the same precedence order and with the same
+/// canonicalization as [`xdg_location_of`]. The system paths are only computed if the iterator gets that far.
+#[cfg(feature = "fs")]
+pub fn xdg_existing_candidates<'a>(
+    xdg_dir: &'a XdgDir,
+    suffix: &'a str,
+) -> impl Iterator<Item = PathBuf> + 'a {
+    let ctx = XdgContext::new();
+    let user_loc = ctx.user_dir(xdg_dir, suffix).ok();
+
+    user_loc
+        .into_iter()
+        .chain(
+            std::iter::once_with(move || ctx.system_dirs(xdg_dir, suffix).unwrap_or_default())
+                .flatten(),
+        )
+        .filter_map(|p| p.canonicalize().ok().filter(|p| p.exists()))
+        // Skip candidates referring to the same location as a previous one
+        .scan(std::collections::HashSet::new(), |seen, p| {
+            Some(seen.insert(p.clone()).then_some(p))
+        })
+        .flatten()
+}
+
+/// List the app-ids with a subdirectory directly under the user-level path of a given XDG basedir, following the
+/// recommended layout where each application owns `$XDG_<BASE>_HOME/<app-id>`. If `include_system` is set, subdirectories
+/// of the system paths are included as well.
+///
+/// The names are sorted and de-duplicated. Missing or unreadable base directories and names that are not valid UTF-8 are
+/// skipped.
+#[cfg(feature = "fs")]
+pub fn xdg_installed_app_ids(xdg_dir: &XdgDir, include_system: bool) -> Vec<String> {
+    let bases = if include_system {
+        xdg_candidate_paths(xdg_dir, "")
+    } else {
+        xdg_user_dir(xdg_dir, "").into_iter().collect()
+    };
+
+    let ids: std::collections::BTreeSet<String> = bases
+        .iter()
+        .filter_map(|base| std::fs::read_dir(base).ok())
+        .flatten()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+
+    ids.into_iter().collect()
+}
+
+/// List the app-id subdirectories of the user cache directory (see [`xdg_installed_app_ids`]) with no matching
+/// subdirectory in any config location, e.g. left behind by uninstalled applications. Nothing is removed.
+///
+/// Note that some applications cache data without having any config, so the results should be confirmed before deleting.
+#[cfg(feature = "fs")]
+pub fn orphaned_cache_dirs() -> Vec<PathBuf> {
+    let Ok(cache_dir) = xdg_user_dir(&dirs::CACHE, "") else {
+        return vec![];
+    };
+    let config_ids = xdg_installed_app_ids(&dirs::CONFIG, true);
+
+    xdg_installed_app_ids(&dirs::CACHE, false)
+        .into_iter()
+        .filter(|id| config_ids.binary_search(id).is_err())
+        .map(|id| cache_dir.join(id))
+        .collect()
+}
+
+/// Returns the first candidate location of `suffix` (user first, then system) that could be written to without
+/// other changes: its parent directory either exists, or could be created because its closest existing ancestor is a
+/// writable directory. Nothing is created, and [`Error::NotFound`] lists the candidates if none qualify.
+///
+/// For typical setups this is the user path, but it handles e.g. `$XDG_CONFIG_HOME` under a missing read-only mount.
+#[cfg(feature = "fs")]
+pub fn xdg_first_creatable(xdg_dir: &XdgDir, suffix: &str) -> Result<PathBuf, Error> {
+    let mut checked = vec![];
+
+    for p in xdg_candidate_paths(xdg_dir, suffix) {
+        checked.push(p.to_string_lossy().to_string());
+
+        let Some(parent) = p.parent() else { continue };
+        if parent.is_dir() {
+            return Ok(p);
+        }
+
+        // Find the closest existing ancestor, which must be a directory we can create entries in
+        if let Some(ancestor) = parent.ancestors().find(|a| a.exists())
+            && ancestor.is_dir()
+            && dir_writable(ancestor)
+        {
+            return Ok(p);
+        }
+    }
+
+    Err(Error::NotFound(suffix.to_string(), checked))
+}
+
+/// Returns the highest precedence candidate directory `suffix` for a given XDG basedir that can actually be written to.
+/// The user directory is tried first and created (along with any missing parents) if it does not exist. System
+/// directories are only used if they already exist and are writable, which is rare but possible in some deployments.
+/// Write access is checked by creating and removing a probe file, rather than from the permissions alone.
+///
+/// Returns [`Error::NotFound`] listing every candidate tried if none is writable.
+#[cfg(feature = "fs")]
+pub fn xdg_writable_dir(xdg_dir: &XdgDir, suffix: &str) -> Result<PathBuf, Error> {
+    let mut checked = vec![];
+    let user = xdg_user_dir(xdg_dir, suffix).ok();
+
+    for dir in xdg_candidate_paths(xdg_dir, suffix) {
+        checked.push(dir.to_string_lossy().to_string());
+
+        // Only the user directory is created
+        if user.as_ref() == Some(&dir) && std::fs::create_dir_all(&dir).is_err() {
+            continue;
+        }
+
+        if dir.is_dir() && probe_writable(&dir) {
+            return Ok(dir);
         }
     }
 
-    // Didn't find it
     Err(Error::NotFound(suffix.to_string(), checked))
 }
+
+/// Check that a file can be created in the directory `dir`, by creating and removing one.
+#[cfg(feature = "fs")]
+fn probe_writable(dir: &Path) -> bool {
+    // A probe left behind by a process that was killed can make the name collide, so retry with a new one
+    for _ in 0..4 {
+        let probe = dir.join(format!(".xdg-dirs-probe-{}", atomic::unique_tag()));
+        match std::fs::File::options()
+            .write(true)
+            .create_new(true)
+            .open(&probe)
+        {
+            Ok(_) => return std::fs::remove_file(&probe).is_ok(),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(_) => return false,
+        }
+    }
+    false
+}
+
+/// Whether the current user can create entries in the directory `dir`.
+#[cfg(all(feature = "fs", unix))]
+fn dir_writable(dir: &Path) -> bool {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    let Ok(dir) = CString::new(dir.as_os_str().as_bytes()) else {
+        return false;
+    };
+    // Safety: `dir` is a valid null-terminated string
+    unsafe { libc::access(dir.as_ptr(), libc::W_OK | libc::X_OK) == 0 }
+}
+
+/// Whether the current user can create entries in the directory `dir`.
+#[cfg(all(feature = "fs", not(unix)))]
+fn dir_writable(dir: &Path) -> bool {
+    dir.metadata().is_ok_and(|m| !m.permissions().readonly())
+}
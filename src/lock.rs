@@ -0,0 +1,55 @@
+//! Lock files in the state directory, for applications that only allow a single running instance.
+
+use std::{
+    fs::{self, File, TryLockError},
+    path::{Path, PathBuf},
+};
+
+use crate::{Error, dirs, io_error, xdg_user_dir};
+
+/// An exclusive lock on a lock file, held until this is dropped.
+#[derive(Debug)]
+pub struct InstanceGuard {
+    file: File,
+    path: PathBuf,
+}
+
+impl InstanceGuard {
+    /// The path of the lock file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for InstanceGuard {
+    fn drop(&mut self) {
+        // The lock is also released when the file is closed, this just makes it explicit
+        let _ = self.file.unlock();
+    }
+}
+
+/// Create and exclusively lock `$XDG_STATE_HOME/<name>.lock` (e.g. with `name` as `app/instance`), creating any missing
+/// parent directories. Returns [`Error::AlreadyLocked`] if another instance holds the lock.
+///
+/// The lock file itself is left in place when the guard is dropped, so it is never deleted out from under another
+/// instance that is about to lock it.
+pub fn xdg_single_instance_lock(name: &str) -> Result<InstanceGuard, Error> {
+    let path = xdg_user_dir(&dirs::STATE, format!("{name}.lock"))?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(io_error(parent))?;
+    }
+
+    let file = File::options()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)
+        .map_err(io_error(&path))?;
+
+    match file.try_lock() {
+        Ok(()) => Ok(InstanceGuard { file, path }),
+        Err(TryLockError::WouldBlock) => Err(Error::AlreadyLocked(path)),
+        Err(TryLockError::Error(e)) => Err(io_error(&path)(e)),
+    }
+}
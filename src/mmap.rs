@@ -0,0 +1,25 @@
+//! Memory-mapping located files, enabled by the `memmap2` feature.
+
+use std::{fs::File, path::PathBuf};
+
+use memmap2::Mmap;
+
+use crate::{Error, XdgDir, io_error, xdg_location_of};
+
+/// Find `suffix` for the given XDG basedir as with [`xdg_location_of`] and map it read-only, returning the path and the
+/// mapping. This avoids reading large resources (e.g. databases) into memory in full.
+///
+/// Returns [`Error::NotFound`] if the file does not exist anywhere, or [`Error::Io`] if it was found but could not be
+/// opened or mapped.
+///
+/// Note that the mapping reflects changes made to the file while it is mapped, and accessing it may crash the process if
+/// the file is truncated by someone else. Only use this for files that are not modified in place.
+pub fn xdg_mmap(xdg_dir: &XdgDir, suffix: &str) -> Result<(PathBuf, Mmap), Error> {
+    let path = xdg_location_of(xdg_dir, suffix)?;
+    let file = File::open(&path).map_err(io_error(&path))?;
+
+    // Safety: documented above, the caller must not use this for files that are modified while mapped
+    let map = unsafe { Mmap::map(&file) }.map_err(io_error(&path))?;
+
+    Ok((path, map))
+}
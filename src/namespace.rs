@@ -0,0 +1,62 @@
+//! An application's config directory, resolved once and used as the base for all its config paths.
+
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "fs")]
+use crate::io_error;
+use crate::{Error, context::push_suffix, xdg_config_dir};
+
+/// An application's user config directory, `$XDG_CONFIG_HOME/<app>`, as returned by [`config_namespace`]. The
+/// environment is only read when it is created, so every path built from it is under the same base.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigNamespace {
+    path: PathBuf,
+}
+
+/// Resolve the user config directory of `app` (i.e. `$XDG_CONFIG_HOME/<app>`) once, to build several paths under it.
+///
+/// ```
+/// if let Ok(config) = xdg_dirs::config_namespace("my-app") {
+///     let settings = config.file("settings.toml");
+///     let themes = config.subdir("themes");
+/// }
+/// ```
+pub fn config_namespace(app: &str) -> Result<ConfigNamespace, Error> {
+    let path = xdg_config_dir(app)?;
+    Ok(ConfigNamespace { path })
+}
+
+impl ConfigNamespace {
+    /// The config directory itself.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The path of the config file `name` in this directory. This does NOT check that it exists.
+    /// Like any other suffix, `name` is relative to the directory even if it starts with a separator.
+    pub fn file(&self, name: impl AsRef<Path>) -> PathBuf {
+        let mut path = self.path.clone();
+        push_suffix(&mut path, name);
+        path
+    }
+
+    /// The subdirectory `name` of this directory, as a namespace of its own.
+    pub fn subdir(&self, name: impl AsRef<Path>) -> ConfigNamespace {
+        ConfigNamespace {
+            path: self.file(name),
+        }
+    }
+
+    /// Create the directory along with any missing parents, succeeding if it already exists.
+    #[cfg(feature = "fs")]
+    pub fn ensure(&self) -> Result<&Path, Error> {
+        std::fs::create_dir_all(&self.path).map_err(io_error(&self.path))?;
+        Ok(&self.path)
+    }
+}
+
+impl AsRef<Path> for ConfigNamespace {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
@@ -0,0 +1,45 @@
+//! Support for relocatable installs, which find bundled resources relative to the running executable.
+
+use std::{env, path::PathBuf};
+
+use crate::{Error, XdgDir, context::push_suffix, xdg_system_dirs};
+
+/// Returns the system paths of a given XDG basedir as with [`xdg_system_dirs`], followed by the equivalent location
+/// under the install prefix of the running executable (e.g. `/opt/app/share/<suffix>` for `/opt/app/bin/app` and `dirs::DATA`).
+///
+/// The prefix is only derived when the executable is directly inside a `bin` directory, and the prefix location is the
+/// lowest precedence and omitted if it duplicates one of the other system paths.
+pub fn xdg_system_dirs_with_self(xdg_dir: &XdgDir, suffix: &str) -> Result<Vec<PathBuf>, Error> {
+    let sys_paths = xdg_system_dirs(xdg_dir, suffix);
+
+    let Some(prefix_path) = xdg_dir
+        .prefix_subdir
+        .zip(install_prefix())
+        .map(|(subdir, prefix)| {
+            let mut path = prefix.join(subdir);
+            push_suffix(&mut path, suffix);
+            path
+        })
+    else {
+        return sys_paths;
+    };
+
+    let mut sys_paths = sys_paths.unwrap_or_default();
+    if !sys_paths.contains(&prefix_path) {
+        sys_paths.push(prefix_path);
+    }
+
+    Ok(sys_paths)
+}
+
+/// The install prefix of the running executable, i.e. the parent of the `bin` directory containing it.
+fn install_prefix() -> Option<PathBuf> {
+    let exe = env::current_exe().ok()?;
+    let bin = exe.parent()?;
+
+    if bin.file_name()? != "bin" {
+        return None;
+    }
+
+    bin.parent().map(PathBuf::from)
+}
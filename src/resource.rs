@@ -0,0 +1,91 @@
+//! Search paths for the well-known resource types defined by the freedesktop.org specifications.
+
+use std::path::PathBuf;
+
+#[cfg(feature = "fs")]
+use crate::context::push_suffix;
+use crate::{XdgContext, XdgDir, dirs};
+
+/// A kind of resource whose location is defined by one of the freedesktop.org specifications.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ResourceType {
+    /// Desktop entries (`.desktop` files) for applications, from the Desktop Entry spec
+    Applications,
+    /// Autostart desktop entries, from the Desktop Application Autostart spec
+    Autostart,
+    /// Desktop directory entries (`.directory` files) used by menus, from the Desktop Menu spec
+    DesktopDirectories,
+    /// Icon themes and unthemed icons, from the Icon Theme spec
+    Icons,
+    /// Menu definitions (`.menu` files), from the Desktop Menu spec
+    Menus,
+    /// The shared MIME-info database, from the Shared MIME-info spec
+    MimeInfo,
+    /// Sound themes, from the Sound Theme spec
+    Sounds,
+}
+
+impl ResourceType {
+    /// The XDG basedir and subdirectory this resource type is located under.
+    fn location(self) -> (&'static XdgDir, &'static str) {
+        match self {
+            ResourceType::Applications => (&dirs::DATA, "applications"),
+            ResourceType::Autostart => (&dirs::CONFIG, "autostart"),
+            ResourceType::DesktopDirectories => (&dirs::DATA, "desktop-directories"),
+            ResourceType::Icons => (&dirs::DATA, "icons"),
+            ResourceType::Menus => (&dirs::CONFIG, "menus"),
+            ResourceType::MimeInfo => (&dirs::DATA, "mime"),
+            ResourceType::Sounds => (&dirs::DATA, "sounds"),
+        }
+    }
+}
+
+/// Returns the search path for a kind of resource in precedence order, as defined by the relevant specification.
+/// This is the resource's subdirectory of each candidate path of the appropriate XDG basedir, plus any extra locations
+/// the spec requires (e.g. `$HOME/.icons` and `/usr/share/pixmaps` for icons).
+/// This does NOT check that any of them exist.
+pub fn xdg_resource_search_path(kind: ResourceType) -> Vec<PathBuf> {
+    let (xdg_dir, subdir) = kind.location();
+    let mut paths = XdgContext::new().candidate_paths(xdg_dir, subdir);
+
+    if kind == ResourceType::Icons {
+        // The icon theme spec searches $HOME/.icons first for backwards compatibility, and /usr/share/pixmaps last
+        if let Some(home) = XdgContext::new().home_dir() {
+            paths.insert(0, home.join(".icons"));
+        }
+        paths.push(PathBuf::from("/usr/share/pixmaps"));
+    }
+
+    paths
+}
+
+/// Returns the directories searched for `xdg-desktop-portal` config (e.g. `portals.conf`) in precedence order:
+/// `xdg-desktop-portal` under each config location, then `/etc/xdg-desktop-portal`, then under each data location.
+/// This does NOT check that any of them exist.
+pub fn portal_config_dirs() -> Vec<PathBuf> {
+    const SUBDIR: &str = "xdg-desktop-portal";
+
+    let mut paths = XdgContext::new()
+        .with_etc_fallback(true)
+        .candidate_paths(&dirs::CONFIG, SUBDIR);
+    for path in XdgContext::new().candidate_paths(&dirs::DATA, SUBDIR) {
+        if !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+
+    paths
+}
+
+/// Find the first existing portal config file `name` (e.g. `gnome-portals.conf`) in [`portal_config_dirs`].
+#[cfg(feature = "fs")]
+pub fn portal_config_file(name: &str) -> Option<PathBuf> {
+    portal_config_dirs()
+        .into_iter()
+        .map(|mut path| {
+            push_suffix(&mut path, name);
+            path
+        })
+        .find(|path| path.is_file())
+}
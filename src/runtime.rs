@@ -0,0 +1,220 @@
+//! Helpers specific to `$XDG_RUNTIME_DIR`, which the spec requires to be private to the user.
+
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use std::{
+    fs::DirBuilder,
+    io,
+    os::unix::fs::{DirBuilderExt, MetadataExt},
+};
+
+use crate::{Env, Error, ProcessEnv, dirs, percent_encode, xdg_user_dir};
+#[cfg(unix)]
+use crate::{context::push_suffix, io_error};
+
+/// Returns the address of the D-Bus session bus: `$DBUS_SESSION_BUS_ADDRESS` if it is set, otherwise the conventional
+/// `unix:path=$XDG_RUNTIME_DIR/bus` if that socket exists.
+pub fn dbus_session_address() -> Option<String> {
+    if let Some(address) = ProcessEnv.get("DBUS_SESSION_BUS_ADDRESS")
+        && !address.is_empty()
+    {
+        return Some(address);
+    }
+
+    let bus = xdg_user_dir(&dirs::RUNTIME, "bus").ok()?;
+    bus.exists().then(|| {
+        // Escape per the D-Bus address format, which only allows a small set of bytes unescaped
+        format!(
+            "unix:path={}",
+            percent_encode(&bus, |b| b"-_/.\\*".contains(&b))
+        )
+    })
+}
+
+/// Returns the path of a Wayland compositor's socket if it exists: `display` (or `$WAYLAND_DISPLAY` if `None`, or
+/// `wayland-0` if that is not set either) under `$XDG_RUNTIME_DIR`. An absolute display name is used as-is.
+pub fn wayland_socket(display: Option<&str>) -> Option<PathBuf> {
+    let display = match display {
+        Some(display) => display.to_string(),
+        None => ProcessEnv
+            .get("WAYLAND_DISPLAY")
+            .filter(|d| !d.is_empty())
+            .unwrap_or_else(|| "wayland-0".to_string()),
+    };
+
+    let socket = if display.starts_with('/') {
+        PathBuf::from(display)
+    } else {
+        xdg_user_dir(&dirs::RUNTIME, &display).ok()?
+    };
+    socket.exists().then_some(socket)
+}
+
+/// Returns the path of the local X server's socket for `$DISPLAY` (e.g. `/tmp/.X11-unix/X0` for `:0`) if it exists.
+/// Note that unlike Wayland, X11 sockets are not in `$XDG_RUNTIME_DIR` but in the fixed `/tmp/.X11-unix` directory.
+/// Returns `None` for a display on a remote host.
+pub fn x11_socket() -> Option<PathBuf> {
+    let display = ProcessEnv.get("DISPLAY")?;
+    let (host, rest) = display.rsplit_once(':')?;
+    if !host.is_empty() && host != "unix" {
+        return None;
+    }
+
+    // Strip the optional screen number, e.g. `:0.1`
+    let number = rest.split_once('.').map_or(rest, |(number, _)| number);
+    if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let socket = PathBuf::from(format!("/tmp/.X11-unix/X{number}"));
+    socket.exists().then_some(socket)
+}
+
+/// A non-fatal problem reported by [`xdg_runtime_dir_lenient`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Warning {
+    /// The environment variable is not set
+    EnvVarNotSet(&'static str),
+    /// This location was used in place of the missing one
+    Substituted(PathBuf),
+}
+
+/// Returns the path of `suffix` in the runtime dir as with [`crate::xdg_runtime_dir`], but treating a missing
+/// `$XDG_RUNTIME_DIR` as a degraded rather than fatal condition. The spec recommends falling back to a replacement
+/// directory with a warning in that case.
+///
+/// If `$XDG_RUNTIME_DIR` is not set, this falls back to the conventional `/run/user/<uid>` on Unix if it exists and is
+/// private to the current user, and returns warnings describing what was missing and what was substituted. The path is
+/// `None` if there is no suitable substitute.
+pub fn xdg_runtime_dir_lenient(suffix: &str) -> (Option<PathBuf>, Vec<Warning>) {
+    match xdg_user_dir(&dirs::RUNTIME, suffix) {
+        Ok(path) => return (Some(path), vec![]),
+        Err(Error::EnvVarNotSet(_)) => {}
+        Err(_) => return (None, vec![]),
+    }
+
+    let mut warnings = vec![Warning::EnvVarNotSet(dirs::RUNTIME.env_var())];
+
+    #[cfg(unix)]
+    if let Some(substitute) = default_runtime_dir() {
+        warnings.push(Warning::Substituted(substitute.clone()));
+        let mut path = substitute;
+        push_suffix(&mut path, suffix);
+        return (Some(path), warnings);
+    }
+
+    (None, warnings)
+}
+
+/// Returns the path of `suffix` in the runtime dir as with [`crate::xdg_runtime_dir`], falling back to the conventional
+/// `/run/user/<uid>` (as created by systemd-logind) if `$XDG_RUNTIME_DIR` is not set. The fallback is only used if it
+/// exists and is a directory owned by the real UID with no group/other permissions, otherwise this still returns
+/// [`Error::EnvVarNotSet`].
+#[cfg(unix)]
+pub fn xdg_runtime_dir_or_default(suffix: impl AsRef<Path>) -> Result<PathBuf, Error> {
+    match xdg_user_dir(&dirs::RUNTIME, &suffix) {
+        Err(Error::EnvVarNotSet(var)) => default_runtime_dir()
+            .map(|mut dir| {
+                push_suffix(&mut dir, suffix);
+                dir
+            })
+            .ok_or(Error::EnvVarNotSet(var)),
+        res => res,
+    }
+}
+
+/// The conventional runtime dir `/run/user/<uid>` for the real UID, if it exists and is private to the user.
+#[cfg(unix)]
+fn default_runtime_dir() -> Option<PathBuf> {
+    user_runtime_dir_in(Path::new("/run/user"))
+}
+
+/// The runtime dir `<base>/<uid>` for the real UID, if it exists and is private to the user.
+#[cfg(unix)]
+fn user_runtime_dir_in(base: &Path) -> Option<PathBuf> {
+    // Safety: getuid has no preconditions and cannot fail
+    let uid = unsafe { libc::getuid() };
+    let dir = base.join(uid.to_string());
+    check_private_dir(&dir).is_ok().then_some(dir)
+}
+
+/// Returns the path of `suffix` in the runtime dir as with [`crate::xdg_runtime_dir`], after checking that the runtime dir
+/// itself is private to the user as the spec requires. On Unix it must be a directory owned by the real UID with no
+/// group/other permissions, otherwise this returns [`Error::InsecureRuntimeDir`]. The check is skipped on other platforms.
+pub fn xdg_runtime_dir_checked(suffix: impl AsRef<Path>) -> Result<PathBuf, Error> {
+    #[cfg(unix)]
+    {
+        let root = xdg_user_dir(&dirs::RUNTIME, "")?;
+        check_private_dir(&root)?;
+    }
+
+    xdg_user_dir(&dirs::RUNTIME, suffix)
+}
+
+/// Returns the private subdirectory `name` of `$XDG_RUNTIME_DIR`, creating it with mode 0700 if it does not exist.
+/// The directory is verified to be owned by the current user and inaccessible to group/other on every call (including
+/// when it already existed), so it is suitable for sockets and secrets even if the runtime dir itself is not.
+#[cfg(unix)]
+pub fn xdg_runtime_private_dir(name: &str) -> Result<PathBuf, Error> {
+    let path = xdg_user_dir(&dirs::RUNTIME, name)?;
+
+    match DirBuilder::new().mode(0o700).create(&path) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+        Err(e) => return Err(io_error(&path)(e)),
+    }
+
+    check_private_dir(&path)?;
+    Ok(path)
+}
+
+/// Check that `path` is a directory (not a symlink to one), owned by the real UID, with no group/other permissions.
+#[cfg(unix)]
+pub(crate) fn check_private_dir(path: &Path) -> Result<(), Error> {
+    let meta = path.symlink_metadata().map_err(io_error(path))?;
+
+    // Safety: getuid has no preconditions and cannot fail
+    let uid = unsafe { libc::getuid() };
+
+    if !meta.is_dir() || meta.uid() != uid || meta.mode() & 0o077 != 0 {
+        return Err(Error::InsecureRuntimeDir(path.to_path_buf()));
+    }
+
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_user_runtime_dir_in() {
+        let base = tempdir().unwrap();
+        // Safety: getuid has no preconditions and cannot fail
+        let dir = base.path().join(unsafe { libc::getuid() }.to_string());
+
+        // Missing
+        assert_eq!(None, user_runtime_dir_in(base.path()));
+
+        fs::create_dir(&dir).unwrap();
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o700)).unwrap();
+        assert_eq!(Some(dir.clone()), user_runtime_dir_in(base.path()));
+
+        // Accessible to group/other
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(None, user_runtime_dir_in(base.path()));
+
+        // A symlink to a private directory
+        let target = base.path().join("target");
+        fs::create_dir(&target).unwrap();
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o700)).unwrap();
+        fs::remove_dir(&dir).unwrap();
+        std::os::unix::fs::symlink(&target, &dir).unwrap();
+        assert_eq!(None, user_runtime_dir_in(base.path()));
+    }
+}
@@ -0,0 +1,95 @@
+//! Fixtures for testing code that depends on the XDG environment, enabled by the `test-support` feature.
+
+use std::{
+    env,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use tempfile::TempDir;
+
+use crate::context::LIST_SEPARATOR;
+
+/// Environment variables set by [`scaffold_xdg_env`], restored when the scaffold is dropped.
+const SCAFFOLD_VARS: [&str; 2] = ["XDG_CONFIG_HOME", "XDG_CONFIG_DIRS"];
+
+/// A temporary XDG environment with one user directory and two system directories.
+/// The directories are deleted and the previous environment is restored when this is dropped.
+pub struct XdgScaffold {
+    root: TempDir,
+    home: PathBuf,
+    system: [PathBuf; 2],
+    saved: Vec<(&'static str, Option<OsString>)>,
+}
+
+impl XdgScaffold {
+    /// The root temporary directory containing all the scaffolded directories.
+    pub fn root(&self) -> &Path {
+        self.root.path()
+    }
+
+    /// The user-level directory, which `$XDG_CONFIG_HOME` points to.
+    pub fn home(&self) -> &Path {
+        &self.home
+    }
+
+    /// The system-level directories, which `$XDG_CONFIG_DIRS` lists in order.
+    pub fn system(&self) -> &[PathBuf; 2] {
+        &self.system
+    }
+}
+
+impl Drop for XdgScaffold {
+    fn drop(&mut self) {
+        for (var, val) in self.saved.drain(..) {
+            // Safety: guaranteed by the caller of `scaffold_xdg_env` for the lifetime of the scaffold
+            match val {
+                Some(val) => unsafe { env::set_var(var, val) },
+                None => unsafe { env::remove_var(var) },
+            }
+        }
+    }
+}
+
+/// Create a temporary home directory and two system directories, and point `$XDG_CONFIG_HOME` and `$XDG_CONFIG_DIRS` at them.
+///
+/// # Panics
+/// If the temporary directories cannot be created.
+///
+/// # Safety
+/// This modifies the process environment both here and when the returned scaffold is dropped, so the same requirements as
+/// [`std::env::set_var`] apply: no other thread may access the environment while this is called or the scaffold is dropped
+/// (e.g. use `#[serial]` tests).
+pub unsafe fn scaffold_xdg_env() -> XdgScaffold {
+    let root = tempfile::tempdir().expect("failed to create temporary directory");
+
+    let home = root.path().join("home");
+    let system = [root.path().join("sys/a"), root.path().join("sys/b")];
+
+    for dir in std::iter::once(&home).chain(&system) {
+        fs::create_dir_all(dir).expect("failed to create scaffold directory");
+    }
+
+    let saved = SCAFFOLD_VARS
+        .iter()
+        .map(|&var| (var, env::var_os(var)))
+        .collect();
+
+    let mut sys_var = OsString::from(system[0].as_os_str());
+    sys_var.push(LIST_SEPARATOR.to_string());
+    sys_var.push(system[1].as_os_str());
+
+    // Safety: guaranteed by the caller
+    unsafe {
+        env::set_var("XDG_CONFIG_HOME", &home);
+        env::set_var("XDG_CONFIG_DIRS", sys_var);
+    }
+
+    XdgScaffold {
+        root,
+        home,
+        system,
+        saved,
+    }
+}
@@ -0,0 +1,60 @@
+//! Disk usage of the files an application keeps in the XDG user directories.
+
+use std::{fs, io, path::Path};
+
+use crate::{Error, dirs, io_error, xdg_user_dir};
+
+/// Bytes used by an application in each XDG user directory, as returned by [`xdg_app_disk_usage`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AppUsage {
+    /// Bytes under `$XDG_CONFIG_HOME/<app>`
+    pub config: u64,
+    /// Bytes under `$XDG_DATA_HOME/<app>`
+    pub data: u64,
+    /// Bytes under `$XDG_CACHE_HOME/<app>`
+    pub cache: u64,
+    /// Bytes under `$XDG_STATE_HOME/<app>`
+    pub state: u64,
+}
+
+impl AppUsage {
+    /// The total bytes used across all the directories.
+    pub fn total(&self) -> u64 {
+        self.config + self.data + self.cache + self.state
+    }
+}
+
+/// Compute the disk usage of the subdirectory `app` of each of the config, data, cache, and state user directories, as
+/// the sum of the sizes of the files under it. A missing directory uses 0 bytes.
+///
+/// Symlinks are counted by their own size and never followed.
+pub fn xdg_app_disk_usage(app: &str) -> Result<AppUsage, Error> {
+    let usage = |xdg_dir| xdg_user_dir(xdg_dir, app).and_then(|path| tree_size(&path));
+
+    Ok(AppUsage {
+        config: usage(&dirs::CONFIG)?,
+        data: usage(&dirs::DATA)?,
+        cache: usage(&dirs::CACHE)?,
+        state: usage(&dirs::STATE)?,
+    })
+}
+
+/// The total size of the files in the tree at `path`, or 0 if it does not exist.
+fn tree_size(path: &Path) -> Result<u64, Error> {
+    let meta = match path.symlink_metadata() {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(io_error(path)(e)),
+    };
+
+    if !meta.is_dir() {
+        return Ok(meta.len());
+    }
+
+    let mut size = 0;
+    for entry in fs::read_dir(path).map_err(io_error(path))? {
+        let entry = entry.map_err(io_error(path))?;
+        size += tree_size(&entry.path())?;
+    }
+    Ok(size)
+}
@@ -0,0 +1,91 @@
+//! Well-known user folders from the [XDG user directories](https://www.freedesktop.org/wiki/Software/xdg-user-dirs/)
+//! configuration, e.g. the downloads folder.
+//!
+//! Each folder is read from `$XDG_CONFIG_HOME/user-dirs.dirs`, which contains lines like
+//! `XDG_DOWNLOAD_DIR="$HOME/Downloads"`, falling back to the environment variable of the same name if the file does not
+//! set it. Values are either absolute or relative to `$HOME`; anything else is ignored.
+
+use std::{fs, path::PathBuf};
+
+use crate::{Error, XdgContext, key_value::parse_key_values, xdg_config_dir};
+
+/// Returns the desktop folder, `$XDG_DESKTOP_DIR`.
+pub fn desktop_dir() -> Result<PathBuf, Error> {
+    user_dir("XDG_DESKTOP_DIR")
+}
+
+/// Returns the downloads folder, `$XDG_DOWNLOAD_DIR`.
+pub fn download_dir() -> Result<PathBuf, Error> {
+    user_dir("XDG_DOWNLOAD_DIR")
+}
+
+/// Returns the documents folder, `$XDG_DOCUMENTS_DIR`.
+pub fn documents_dir() -> Result<PathBuf, Error> {
+    user_dir("XDG_DOCUMENTS_DIR")
+}
+
+/// Returns the music folder, `$XDG_MUSIC_DIR`.
+pub fn music_dir() -> Result<PathBuf, Error> {
+    user_dir("XDG_MUSIC_DIR")
+}
+
+/// Returns the pictures folder, `$XDG_PICTURES_DIR`.
+pub fn pictures_dir() -> Result<PathBuf, Error> {
+    user_dir("XDG_PICTURES_DIR")
+}
+
+/// Returns the videos folder, `$XDG_VIDEOS_DIR`.
+pub fn videos_dir() -> Result<PathBuf, Error> {
+    user_dir("XDG_VIDEOS_DIR")
+}
+
+/// Returns the templates folder, `$XDG_TEMPLATES_DIR`.
+pub fn templates_dir() -> Result<PathBuf, Error> {
+    user_dir("XDG_TEMPLATES_DIR")
+}
+
+/// Returns the public share folder, `$XDG_PUBLICSHARE_DIR`.
+pub fn publicshare_dir() -> Result<PathBuf, Error> {
+    user_dir("XDG_PUBLICSHARE_DIR")
+}
+
+/// Resolve the user folder `var` from `user-dirs.dirs`, or the environment if the file does not set it.
+/// Returns [`Error::EnvVarNotSet`] if neither has a valid value.
+fn user_dir(var: &'static str) -> Result<PathBuf, Error> {
+    let ctx = XdgContext::new();
+
+    // The file is sourced as a shell script, so the last assignment wins
+    let from_file = xdg_config_dir("user-dirs.dirs")
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| {
+            parse_key_values(&contents)
+                .into_iter()
+                .rev()
+                .find(|(key, _)| key == var)
+        })
+        .map(|(_, value)| value);
+
+    // An invalid value is treated the same as it not being set
+    let expand = |value: String| expand_home(&ctx, &value).transpose();
+    from_file
+        .and_then(expand)
+        .or_else(|| expand(ctx.env_value(var)?.into_string().ok()?))
+        .unwrap_or(Err(Error::EnvVarNotSet(var)))
+}
+
+/// Expand a value of the form `$HOME/<path>` (or just `$HOME`), or accept an absolute path. Returns `None` for anything
+/// else, which the spec does not allow.
+fn expand_home(ctx: &XdgContext, value: &str) -> Result<Option<PathBuf>, Error> {
+    match value.strip_prefix("$HOME") {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+            let mut path = ctx.home_dir().ok_or(Error::NoHome)?;
+            let rest = rest.trim_start_matches('/');
+            if !rest.is_empty() {
+                path.push(rest);
+            }
+            Ok(Some(path))
+        }
+        _ => Ok(Some(PathBuf::from(value)).filter(|p| p.is_absolute())),
+    }
+}
@@ -0,0 +1,53 @@
+//! Config stored in versioned or per-profile subdirectories, e.g. `$XDG_CONFIG_HOME/app/v2/config.toml` for apps that
+//! version their config schema.
+
+use std::path::PathBuf;
+
+use crate::{Error, XdgContext, xdg_config_dir};
+
+/// Returns the path of the config file `name` for version `version` of `app`'s config schema, i.e.
+/// `$XDG_CONFIG_HOME/<app>/v<version>/<name>`. This does NOT check that it exists.
+pub fn xdg_versioned_config(app: &str, version: u32, name: &str) -> Result<PathBuf, Error> {
+    xdg_config_dir(format!("{app}/v{version}/{name}"))
+}
+
+/// Find the highest version with an existing `$XDG_CONFIG_HOME/<app>/v<version>` directory, e.g. to migrate from it to
+/// the current version. Returns `None` if there are no version directories.
+#[cfg(feature = "fs")]
+pub fn xdg_latest_config_version(app: &str) -> Option<u32> {
+    let app_dir = xdg_config_dir(app).ok()?;
+
+    std::fs::read_dir(app_dir)
+        .ok()?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().to_str()?.strip_prefix('v')?.parse().ok())
+        .max()
+}
+
+/// Returns the path of the config file `name` for the profile selected by the environment variable `profile_var` (e.g.
+/// `MYAPP_PROFILE=work`), i.e. `$XDG_CONFIG_HOME/<base>/<profile>/<name>`, or `$XDG_CONFIG_HOME/<base>/<name>` if the
+/// variable is not set or empty. This does NOT check that it exists.
+///
+/// The profile must be a single path component, so that it cannot refer to a location outside `<base>`. A value
+/// containing path separators, or that is `.` or `..`, is treated the same as the variable not being set.
+pub fn xdg_profiled_config(
+    profile_var: &'static str,
+    base: &str,
+    name: &str,
+) -> Result<PathBuf, Error> {
+    let profile = XdgContext::new()
+        .env_value(profile_var)
+        .and_then(|profile| profile.into_string().ok())
+        .filter(|profile| valid_profile(profile));
+
+    match profile {
+        Some(profile) => xdg_config_dir(format!("{base}/{profile}/{name}")),
+        None => xdg_config_dir(format!("{base}/{name}")),
+    }
+}
+
+/// Whether `profile` is a single, normal path component.
+fn valid_profile(profile: &str) -> bool {
+    profile != "." && profile != ".." && !profile.contains(['/', '\\'])
+}
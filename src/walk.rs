@@ -0,0 +1,79 @@
+//! Recursive traversal of directory trees across all candidate locations of an XDG basedir.
+
+use std::{
+    fs::{self, DirEntry},
+    path::{Path, PathBuf},
+    vec,
+};
+
+use crate::{XdgContext, XdgDir};
+
+/// A file found by [`xdg_walk`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalkEntry {
+    /// The full path of the file
+    pub path: PathBuf,
+    /// The path of the file relative to the candidate directory it was found under.
+    /// The same relative path may be yielded once per candidate directory, the first occurrence has the highest precedence.
+    pub relative: PathBuf,
+}
+
+/// Lazily walk the directory tree under `subdir` in each candidate location of the given XDG basedir, yielding every
+/// non-directory entry. Candidate directories are walked one at a time in precedence order (user first, then system),
+/// and the entries of each directory in order of file name, so the output is reproducible.
+///
+/// Symlinks are yielded as entries but never followed into directories, and unreadable directories are skipped.
+pub fn xdg_walk(xdg_dir: &XdgDir, subdir: &str) -> impl Iterator<Item = WalkEntry> + use<> {
+    XdgContext::new()
+        .candidate_paths(xdg_dir, subdir)
+        .into_iter()
+        .flat_map(TreeWalk::new)
+}
+
+/// Depth-first walk of a single directory tree.
+struct TreeWalk {
+    root: PathBuf,
+    stack: Vec<vec::IntoIter<DirEntry>>,
+}
+
+impl TreeWalk {
+    fn new(root: PathBuf) -> Self {
+        let stack = sorted_entries(&root).into_iter().collect();
+        TreeWalk { root, stack }
+    }
+}
+
+/// The readable entries of the directory at `path` sorted by file name, or `None` if it cannot be read.
+fn sorted_entries(path: &Path) -> Option<vec::IntoIter<DirEntry>> {
+    let mut entries: Vec<_> = fs::read_dir(path).ok()?.filter_map(Result::ok).collect();
+    entries.sort_by_key(DirEntry::file_name);
+    Some(entries.into_iter())
+}
+
+impl Iterator for TreeWalk {
+    type Item = WalkEntry;
+
+    fn next(&mut self) -> Option<WalkEntry> {
+        while let Some(entries) = self.stack.last_mut() {
+            let Some(entry) = entries.next() else {
+                self.stack.pop();
+                continue;
+            };
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+
+            let path = entry.path();
+            if file_type.is_dir() {
+                if let Some(sub_entries) = sorted_entries(&path) {
+                    self.stack.push(sub_entries);
+                }
+            } else {
+                let relative = path.strip_prefix(&self.root).unwrap_or(&path).to_path_buf();
+                return Some(WalkEntry { path, relative });
+            }
+        }
+
+        None
+    }
+}
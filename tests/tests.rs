@@ -1,13 +1,14 @@
+#[cfg(feature = "fs")]
 use tempfile::tempdir;
 #[cfg(test)]
 use xdg_dirs::*;
 
 use serial_test::serial;
+#[cfg(feature = "fs")]
+use std::fs::{self, File};
 use std::{
     env,
-    fs::{self, File},
-    path::Path,
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
 #[test]
@@ -46,6 +47,41 @@ fn test_user_data_dir() {
     );
 }
 
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_user_bin_dir() {
+    unsafe { env::set_var("XDG_BIN_HOME", "/some/path") };
+    assert_eq!(
+        xdg_user_dir(&dirs::BIN, "test").unwrap(),
+        Path::new("/some/path/test")
+    );
+    assert_eq!(xdg_bin_dir("test").unwrap(), Path::new("/some/path/test"));
+
+    unsafe { env::remove_var("XDG_BIN_HOME") };
+    unsafe { env::set_var("HOME", "/some/home") };
+    assert_eq!(
+        xdg_user_dir(&dirs::BIN, "test").unwrap(),
+        Path::new("/some/home/.local/bin/test")
+    );
+    assert_eq!(
+        xdg_system_dirs(&dirs::BIN, "test"),
+        Err(Error::SystemDirNotApplicable("executables"))
+    );
+}
+
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_user_config_dir_empty() {
+    unsafe { env::set_var("XDG_CONFIG_HOME", "") };
+    unsafe { env::set_var("HOME", "/some/home") };
+    assert_eq!(
+        xdg_user_dir(&dirs::CONFIG, "test").unwrap(),
+        Path::new("/some/home/.config/test")
+    );
+}
+
 #[test]
 // Safety: serial because env var access must be single-threaded (even with different vars)
 #[serial]
@@ -55,6 +91,15 @@ fn test_user_config_dir_no_home() {
     assert_eq!(xdg_user_dir(&dirs::CONFIG, "test"), Err(Error::NoHome));
 }
 
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_relative_home_ignored() {
+    unsafe { env::remove_var("XDG_CONFIG_HOME") };
+    unsafe { env::set_var("HOME", "relative/home") };
+    assert_eq!(xdg_user_dir(&dirs::CONFIG, "test"), Err(Error::NoHome));
+}
+
 #[test]
 // Safety: serial because env var access must be single-threaded (even with different vars)
 #[serial]
@@ -138,6 +183,7 @@ fn test_error_display() {
     assert_eq!(err.to_string(), "$SOME_VAR is not set");
 }
 
+#[cfg(feature = "fs")]
 #[test]
 // Safety: serial because env var access must be single-threaded (even with different vars)
 #[serial]
@@ -192,6 +238,7 @@ fn test_xdg_location_of_config() {
     assert_eq!(fh, xdg_location_of(&dirs::CONFIG, suffix).unwrap());
 }
 
+#[cfg(feature = "fs")]
 #[test]
 // Safety: serial because env var access must be single-threaded (even with different vars)
 #[serial]
@@ -222,3 +269,3214 @@ fn test_xdg_location_of_cache() {
 
     assert_eq!(fh, xdg_location_of(&dirs::CACHE, suffix).unwrap());
 }
+
+#[test]
+fn test_schema() {
+    assert_eq!(
+        dirs::CONFIG.schema("app"),
+        Schema {
+            user_template: "$XDG_CONFIG_HOME/app".to_string(),
+            user_default: Some("~/.config/app".to_string()),
+            system_template: Some("$XDG_CONFIG_DIRS/app".to_string()),
+            system_defaults: vec!["/etc/xdg/app".to_string()],
+        }
+    );
+
+    assert_eq!(
+        dirs::RUNTIME.schema("app"),
+        Schema {
+            user_template: "$XDG_RUNTIME_DIR/app".to_string(),
+            user_default: None,
+            system_template: None,
+            system_defaults: vec![],
+        }
+    );
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_xdg_read_write_paths() {
+    let test_dir = PathBuf::from(tempdir().unwrap().path());
+
+    let home_dir = test_dir.join("home");
+    let sys_dir = test_dir.join("sys");
+
+    fs::create_dir_all(home_dir.clone()).unwrap();
+    fs::create_dir_all(sys_dir.clone()).unwrap();
+
+    unsafe { env::set_var("XDG_CONFIG_HOME", home_dir.clone()) };
+    unsafe { env::set_var("XDG_CONFIG_DIRS", sys_dir.clone()) };
+
+    let suffix = "xyz";
+    let fh = home_dir.join(suffix);
+    let fs = sys_dir.join(suffix);
+
+    assert_eq!(
+        (None, fh.clone()),
+        xdg_read_write_paths(&dirs::CONFIG, suffix).unwrap()
+    );
+
+    File::create(fs.clone()).unwrap();
+    assert_eq!(
+        (Some(fs), fh.clone()),
+        xdg_read_write_paths(&dirs::CONFIG, suffix).unwrap()
+    );
+
+    File::create(fh.clone()).unwrap();
+    assert_eq!(
+        (Some(fh.clone()), fh),
+        xdg_read_write_paths(&dirs::CONFIG, suffix).unwrap()
+    );
+}
+
+#[cfg(all(feature = "fs", unix))]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_xdg_runtime_private_dir() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let test_dir = tempdir().unwrap();
+    unsafe { env::set_var("XDG_RUNTIME_DIR", test_dir.path()) };
+
+    let expected = test_dir.path().join("app");
+    assert_eq!(expected, xdg_runtime_private_dir("app").unwrap());
+    assert_eq!(
+        0o700,
+        fs::metadata(&expected).unwrap().permissions().mode() & 0o777
+    );
+
+    // Verified again if it already exists
+    assert_eq!(expected, xdg_runtime_private_dir("app").unwrap());
+
+    fs::set_permissions(&expected, fs::Permissions::from_mode(0o755)).unwrap();
+    assert_eq!(
+        Err(Error::InsecureRuntimeDir(expected)),
+        xdg_runtime_private_dir("app")
+    );
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_xdg_validate_user_dir() {
+    let test_dir = tempdir().unwrap();
+
+    unsafe { env::set_var("XDG_CONFIG_HOME", test_dir.path()) };
+    assert_eq!(
+        test_dir.path(),
+        xdg_validate_user_dir(&dirs::CONFIG).unwrap()
+    );
+
+    let missing = test_dir.path().join("missing");
+    unsafe { env::set_var("XDG_CONFIG_HOME", missing.clone()) };
+    assert_eq!(missing, xdg_validate_user_dir(&dirs::CONFIG).unwrap());
+
+    let file = test_dir.path().join("file");
+    File::create(file.clone()).unwrap();
+    unsafe { env::set_var("XDG_CONFIG_HOME", file.clone()) };
+    let res = xdg_validate_user_dir(&dirs::CONFIG);
+    assert_eq!(
+        Err(Error::BaseNotADirectory("XDG_CONFIG_HOME", file.clone())),
+        res
+    );
+    assert_eq!(
+        format!(
+            "{:?} is used as the base directory for $XDG_CONFIG_HOME, but is not a directory",
+            file
+        ),
+        res.unwrap_err().to_string()
+    );
+}
+
+#[cfg(feature = "test-support")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_scaffold_xdg_env() {
+    unsafe { env::set_var("XDG_CONFIG_HOME", "/some/path") };
+    unsafe { env::remove_var("XDG_CONFIG_DIRS") };
+
+    let scaffold = unsafe { scaffold_xdg_env() };
+    let [sysa, sysb] = scaffold.system().clone();
+
+    assert_eq!(
+        scaffold.home().join("xyz"),
+        xdg_user_dir(&dirs::CONFIG, "xyz").unwrap()
+    );
+    assert_eq!(
+        vec![sysa.join("xyz"), sysb.join("xyz")],
+        xdg_system_dirs(&dirs::CONFIG, "xyz").unwrap()
+    );
+
+    let fb = sysb.join("xyz");
+    File::create(fb.clone()).unwrap();
+    assert_eq!(fb, xdg_location_of(&dirs::CONFIG, "xyz").unwrap());
+
+    let root = scaffold.root().to_path_buf();
+    drop(scaffold);
+
+    assert!(!root.exists());
+    assert_eq!(env::var_os("XDG_CONFIG_HOME").unwrap(), "/some/path");
+    assert_eq!(env::var_os("XDG_CONFIG_DIRS"), None);
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_suffix_collisions() {
+    let test_dir = tempdir().unwrap();
+
+    let config_dir = test_dir.path().join("config");
+    let data_dir = test_dir.path().join("data");
+    let cache_dir = test_dir.path().join("cache");
+
+    fs::create_dir_all(config_dir.clone()).unwrap();
+    fs::create_dir_all(data_dir.clone()).unwrap();
+    fs::create_dir_all(cache_dir.clone()).unwrap();
+
+    unsafe { env::set_var("XDG_CONFIG_HOME", config_dir.clone()) };
+    unsafe { env::set_var("XDG_CONFIG_DIRS", test_dir.path().join("sys")) };
+    unsafe { env::set_var("XDG_DATA_HOME", data_dir.clone()) };
+    unsafe { env::set_var("XDG_DATA_DIRS", test_dir.path().join("sys")) };
+    unsafe { env::set_var("XDG_CACHE_HOME", cache_dir.clone()) };
+    unsafe { env::set_var("XDG_STATE_HOME", test_dir.path().join("state")) };
+    unsafe { env::remove_var("XDG_RUNTIME_DIR") };
+
+    let suffix = "xyz";
+    assert!(suffix_collisions(suffix).is_empty());
+
+    File::create(config_dir.join(suffix)).unwrap();
+    File::create(cache_dir.join(suffix)).unwrap();
+
+    let found: Vec<_> = suffix_collisions(suffix)
+        .into_iter()
+        .map(|(xdg_dir, path)| (xdg_dir.schema("").user_template, path))
+        .collect();
+    assert_eq!(
+        vec![
+            ("$XDG_CONFIG_HOME".to_string(), config_dir.join(suffix)),
+            ("$XDG_CACHE_HOME".to_string(), cache_dir.join(suffix)),
+        ],
+        found
+    );
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_xdg_location_uri() {
+    let test_dir = tempdir().unwrap();
+    let home_dir = test_dir.path().join("home dir");
+    fs::create_dir_all(home_dir.clone()).unwrap();
+
+    unsafe { env::set_var("XDG_DATA_HOME", home_dir.clone()) };
+    unsafe { env::set_var("XDG_DATA_DIRS", test_dir.path().join("sys")) };
+
+    let suffix = "caf\u{e9}#1.txt";
+    assert!(matches!(
+        xdg_location_uri(&dirs::DATA, suffix),
+        Err(Error::NotFound(..))
+    ));
+
+    File::create(home_dir.join(suffix)).unwrap();
+    assert_eq!(
+        format!(
+            "file://{}/home%20dir/caf%C3%A9%231.txt",
+            test_dir.path().canonicalize().unwrap().display()
+        ),
+        xdg_location_uri(&dirs::DATA, suffix).unwrap()
+    );
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_xdg_walk() {
+    let test_dir = tempdir().unwrap();
+
+    let home_dir = test_dir.path().join("home");
+    let sysa = test_dir.path().join("sys/a");
+    let sysb = test_dir.path().join("sys/b");
+
+    fs::create_dir_all(home_dir.join("app/res/nested/deeper")).unwrap();
+    fs::create_dir_all(sysa.join("app/res")).unwrap();
+    fs::create_dir_all(sysb.join("other")).unwrap();
+
+    File::create(home_dir.join("app/res/top.txt")).unwrap();
+    File::create(home_dir.join("app/res/nested/deeper/leaf.txt")).unwrap();
+    File::create(sysa.join("app/res/top.txt")).unwrap();
+    File::create(sysb.join("other/ignored.txt")).unwrap();
+
+    unsafe { env::set_var("XDG_DATA_HOME", home_dir.clone()) };
+    unsafe {
+        env::set_var(
+            "XDG_DATA_DIRS",
+            format!("{0}:{1}", sysa.display(), sysb.display()),
+        )
+    };
+
+    let mut home_entries: Vec<_> = xdg_walk(&dirs::DATA, "app/res").collect();
+    let sys_entry = home_entries.pop().unwrap();
+    home_entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+    assert_eq!(
+        vec![
+            WalkEntry {
+                path: home_dir.join("app/res/nested/deeper/leaf.txt"),
+                relative: PathBuf::from("nested/deeper/leaf.txt"),
+            },
+            WalkEntry {
+                path: home_dir.join("app/res/top.txt"),
+                relative: PathBuf::from("top.txt"),
+            },
+        ],
+        home_entries
+    );
+    assert_eq!(
+        WalkEntry {
+            path: sysa.join("app/res/top.txt"),
+            relative: PathBuf::from("top.txt"),
+        },
+        sys_entry
+    );
+}
+
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_check_path_max() {
+    unsafe { env::set_var("XDG_DATA_HOME", "/some/path") };
+    assert_eq!(
+        Path::new("/some/path/test"),
+        xdg_user_dir(&dirs::DATA, "test")
+            .and_then(check_path_max)
+            .unwrap()
+    );
+
+    let long = "x".repeat(PATH_MAX);
+    assert_eq!(
+        Err(Error::PathTooLong(Path::new("/some/path").join(&long))),
+        xdg_user_dir(&dirs::DATA, &long).and_then(check_path_max)
+    );
+
+    let long_name = "x".repeat(NAME_MAX + 1);
+    assert_eq!(
+        Err(Error::NameTooLong(Path::new("/some/path").join(&long_name))),
+        xdg_user_dir(&dirs::DATA, &long_name).and_then(check_path_max)
+    );
+
+    // Applied to every resolved path when enabled on the context
+    unsafe { env::set_var("XDG_DATA_DIRS", "/usr/share") };
+    let ctx = XdgContext::new().with_length_check(true);
+    assert_eq!(
+        Err(Error::NameTooLong(Path::new("/some/path").join(&long_name))),
+        ctx.user_dir(&dirs::DATA, &long_name)
+    );
+    assert_eq!(
+        Err(Error::NameTooLong(Path::new("/usr/share").join(&long_name))),
+        ctx.system_dirs(&dirs::DATA, &long_name)
+    );
+    assert_eq!(
+        Ok(vec![PathBuf::from("/usr/share/test")]),
+        ctx.system_dirs(&dirs::DATA, "test")
+    );
+    assert!(XdgContext::new().user_dir(&dirs::DATA, &long_name).is_ok());
+}
+
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_xdg_user_override_path() {
+    unsafe { env::set_var("XDG_DATA_HOME", "/some/path") };
+    assert_eq!(
+        Path::new("/some/path/icons"),
+        xdg_user_override_path(&dirs::DATA, "icons").unwrap()
+    );
+
+    assert_eq!(
+        Err(Error::SystemDirNotApplicable("state")),
+        xdg_user_override_path(&dirs::STATE, "icons")
+    );
+}
+
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_context_no_home_fallback() {
+    unsafe { env::remove_var("XDG_CONFIG_HOME") };
+    unsafe { env::set_var("HOME", "/some/home") };
+
+    let ctx = XdgContext::new().no_home_fallback();
+    assert_eq!(
+        Err(Error::EnvVarNotSet("XDG_CONFIG_HOME")),
+        ctx.user_dir(&dirs::CONFIG, "test")
+    );
+    assert_eq!(
+        Path::new("/some/home/.config/test"),
+        XdgContext::new().user_dir(&dirs::CONFIG, "test").unwrap()
+    );
+
+    unsafe { env::set_var("XDG_CONFIG_HOME", "/some/path") };
+    assert_eq!(
+        Path::new("/some/path/test"),
+        ctx.user_dir(&dirs::CONFIG, "test").unwrap()
+    );
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_xdg_config_or_legacy() {
+    let test_dir = tempdir().unwrap();
+    let home_dir = test_dir.path().join("home");
+    let config_dir = home_dir.join(".config");
+    fs::create_dir_all(config_dir.clone()).unwrap();
+
+    unsafe { env::set_var("HOME", home_dir.clone()) };
+    unsafe { env::remove_var("XDG_CONFIG_HOME") };
+    unsafe { env::set_var("XDG_CONFIG_DIRS", test_dir.path().join("sys")) };
+
+    assert_eq!(
+        Err(Error::NotFound(
+            "myapp".to_string(),
+            vec![
+                config_dir.join("myapp").to_string_lossy().to_string(),
+                test_dir
+                    .path()
+                    .join("sys/myapp")
+                    .to_string_lossy()
+                    .to_string(),
+                home_dir.join(".myapp").to_string_lossy().to_string(),
+            ]
+        )),
+        xdg_config_or_legacy(".myapp", "myapp")
+    );
+
+    fs::create_dir(home_dir.join(".myapp")).unwrap();
+    assert_eq!(
+        (home_dir.join(".myapp"), true),
+        xdg_config_or_legacy(".myapp", "myapp").unwrap()
+    );
+
+    // The legacy path takes precedence over a copy in the system paths
+    fs::create_dir_all(test_dir.path().join("sys/myapp")).unwrap();
+    assert_eq!(
+        (home_dir.join(".myapp"), true),
+        xdg_config_or_legacy(".myapp", "myapp").unwrap()
+    );
+
+    fs::create_dir(config_dir.join("myapp")).unwrap();
+    assert_eq!(
+        (config_dir.join("myapp"), false),
+        xdg_config_or_legacy(".myapp", "myapp").unwrap()
+    );
+
+    // The system paths are still used when there is no legacy path
+    fs::remove_dir(config_dir.join("myapp")).unwrap();
+    fs::remove_dir(home_dir.join(".myapp")).unwrap();
+    assert_eq!(
+        (test_dir.path().join("sys/myapp"), false),
+        xdg_config_or_legacy(".myapp", "myapp").unwrap()
+    );
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_xdg_migrate_legacy() {
+    let test_dir = tempdir().unwrap();
+    let home_dir = test_dir.path().join("home");
+    let legacy = home_dir.join(".myapp");
+    let target = home_dir.join(".local/share/myapp");
+
+    fs::create_dir_all(home_dir.clone()).unwrap();
+    unsafe { env::set_var("HOME", home_dir.clone()) };
+    unsafe { env::remove_var("XDG_DATA_HOME") };
+
+    assert_eq!(
+        MigrationResult::NoLegacy,
+        xdg_migrate_legacy(".myapp", &dirs::DATA, "myapp").unwrap()
+    );
+
+    fs::create_dir(legacy.clone()).unwrap();
+    fs::write(legacy.join("data.txt"), "contents").unwrap();
+
+    assert_eq!(
+        MigrationResult::Moved {
+            from: legacy.clone(),
+            to: target.clone()
+        },
+        xdg_migrate_legacy(".myapp", &dirs::DATA, "myapp").unwrap()
+    );
+    assert!(!legacy.exists());
+    assert_eq!(
+        "contents",
+        fs::read_to_string(target.join("data.txt")).unwrap()
+    );
+
+    // Never overwrites the XDG location
+    fs::create_dir(legacy.clone()).unwrap();
+    assert_eq!(
+        MigrationResult::AlreadyExists(target.clone()),
+        xdg_migrate_legacy(".myapp", &dirs::DATA, "myapp").unwrap()
+    );
+    assert!(legacy.exists());
+
+    // A single file is moved too
+    fs::write(home_dir.join(".myapprc"), "rc").unwrap();
+    assert_eq!(
+        MigrationResult::Moved {
+            from: home_dir.join(".myapprc"),
+            to: target.join("rc")
+        },
+        xdg_migrate_legacy(".myapprc", &dirs::DATA, "myapp/rc").unwrap()
+    );
+    assert_eq!("rc", fs::read_to_string(target.join("rc")).unwrap());
+}
+
+#[cfg(all(feature = "fs", target_os = "linux"))]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_xdg_migrate_legacy_across_filesystems() {
+    // /dev/shm is normally a separate tmpfs, so the migration has to copy rather than rename
+    let Ok(data_dir) = tempfile::tempdir_in("/dev/shm") else {
+        return;
+    };
+    let test_dir = tempdir().unwrap();
+    let home_dir = test_dir.path().join("home");
+    let legacy = home_dir.join(".myapp");
+    fs::create_dir_all(legacy.join("sub")).unwrap();
+    fs::write(legacy.join("sub/data.txt"), "contents").unwrap();
+    fs::write(home_dir.join(".myapprc"), "rc").unwrap();
+    unsafe {
+        env::set_var("HOME", &home_dir);
+        env::set_var("XDG_DATA_HOME", data_dir.path());
+    }
+
+    let target = data_dir.path().join("myapp");
+    assert_eq!(
+        MigrationResult::Moved {
+            from: legacy.clone(),
+            to: target.clone()
+        },
+        xdg_migrate_legacy(".myapp", &dirs::DATA, "myapp").unwrap()
+    );
+    assert!(!legacy.exists());
+    assert_eq!(
+        "contents",
+        fs::read_to_string(target.join("sub/data.txt")).unwrap()
+    );
+
+    assert_eq!(
+        MigrationResult::Moved {
+            from: home_dir.join(".myapprc"),
+            to: data_dir.path().join("myapprc")
+        },
+        xdg_migrate_legacy(".myapprc", &dirs::DATA, "myapprc").unwrap()
+    );
+    assert_eq!(
+        "rc",
+        fs::read_to_string(data_dir.path().join("myapprc")).unwrap()
+    );
+}
+
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_xdg_system_dirs_with_self() {
+    // The test binary is not installed in a `bin` directory, so there is no install prefix
+    unsafe { env::set_var("XDG_DATA_DIRS", "/some/path") };
+    assert_eq!(
+        vec![Path::new("/some/path/test")],
+        xdg_system_dirs_with_self(&dirs::DATA, "test").unwrap()
+    );
+
+    assert_eq!(
+        Err(Error::SystemDirNotApplicable("cache")),
+        xdg_system_dirs_with_self(&dirs::CACHE, "test")
+    );
+}
+
+#[test]
+fn test_send_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send_sync::<XdgDir>();
+    assert_send_sync::<XdgContext>();
+    assert_send_sync::<Schema>();
+    assert_send_sync::<Error>();
+    assert_send_sync::<ProcessEnv>();
+    assert_send_sync::<XdgEnv>();
+    assert_send_sync::<ResolutionDiff>();
+    assert_send_sync::<ResolvedDir>();
+    assert_send_sync::<ConfigNamespace>();
+    assert_send_sync::<CandidateStatus>();
+    assert_send_sync::<Candidates>();
+    assert_send_sync::<XdgCandidates<'static>>();
+    #[cfg(feature = "fs")]
+    assert_send_sync::<WalkEntry>();
+    #[cfg(feature = "fs")]
+    assert_send_sync::<AppUsage>();
+    #[cfg(feature = "fs")]
+    assert_send_sync::<Warning>();
+    #[cfg(feature = "fs")]
+    assert_send_sync::<MigrationResult>();
+    #[cfg(feature = "fs")]
+    assert_send_sync::<InstanceGuard>();
+    #[cfg(feature = "test-support")]
+    assert_send_sync::<XdgScaffold>();
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_dbus_session_address() {
+    let test_dir = tempdir().unwrap();
+    let runtime_dir = test_dir.path().join("run time");
+    fs::create_dir_all(runtime_dir.clone()).unwrap();
+
+    unsafe { env::remove_var("DBUS_SESSION_BUS_ADDRESS") };
+    unsafe { env::set_var("XDG_RUNTIME_DIR", runtime_dir.clone()) };
+    assert_eq!(None, dbus_session_address());
+
+    File::create(runtime_dir.join("bus")).unwrap();
+    assert_eq!(
+        Some(format!(
+            "unix:path={}/run%20time/bus",
+            test_dir.path().display()
+        )),
+        dbus_session_address()
+    );
+
+    unsafe { env::set_var("DBUS_SESSION_BUS_ADDRESS", "unix:path=/some/bus") };
+    assert_eq!(
+        Some("unix:path=/some/bus".to_string()),
+        dbus_session_address()
+    );
+}
+
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_relative_between() {
+    unsafe { env::remove_var("XDG_CONFIG_HOME") };
+    unsafe { env::remove_var("XDG_DATA_HOME") };
+    unsafe { env::set_var("HOME", "/some/home") };
+
+    assert_eq!(
+        Path::new("../../.local/share/app/data"),
+        relative_between(&dirs::CONFIG, "app", &dirs::DATA, "app/data").unwrap()
+    );
+    assert_eq!(
+        Path::new("sub/file"),
+        relative_between(&dirs::CONFIG, "app", &dirs::CONFIG, "app/sub/file").unwrap()
+    );
+
+    unsafe { env::set_var("XDG_DATA_HOME", "/data") };
+    assert_eq!(
+        Path::new("../../../../data/app"),
+        relative_between(&dirs::CONFIG, "app", &dirs::DATA, "app").unwrap()
+    );
+}
+
+#[cfg(all(feature = "toml", feature = "json"))]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_xdg_load_config() {
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct Config {
+        name: String,
+    }
+
+    let test_dir = tempdir().unwrap();
+    let home_dir = test_dir.path().join("home");
+    fs::create_dir_all(home_dir.join("app")).unwrap();
+
+    unsafe { env::set_var("XDG_CONFIG_HOME", home_dir.clone()) };
+    unsafe { env::set_var("XDG_CONFIG_DIRS", test_dir.path().join("sys")) };
+
+    assert!(matches!(
+        xdg_load_config::<Config>("app/config"),
+        Err(Error::NotFound(..))
+    ));
+
+    fs::write(home_dir.join("app/config.json"), r#"{"name": "json"}"#).unwrap();
+    assert_eq!(
+        Config {
+            name: "json".to_string()
+        },
+        xdg_load_config("app/config").unwrap()
+    );
+
+    // TOML is preferred, but falls through to JSON if it fails to parse
+    let toml_path = home_dir.join("app/config.toml");
+    fs::write(toml_path.clone(), "name = ").unwrap();
+    assert_eq!(
+        Config {
+            name: "json".to_string()
+        },
+        xdg_load_config("app/config").unwrap()
+    );
+
+    let Err(Error::Parse(failures)) = xdg_load_config::<Config>("app/config.toml") else {
+        panic!("expected parse error");
+    };
+    assert_eq!(
+        vec![toml_path],
+        failures.into_iter().map(|(p, _)| p).collect::<Vec<_>>()
+    );
+
+    fs::write(home_dir.join("app/config.toml"), r#"name = "toml""#).unwrap();
+    assert_eq!(
+        Config {
+            name: "toml".to_string()
+        },
+        xdg_load_config("app/config").unwrap()
+    );
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_xdg_existing_candidates() {
+    let test_dir = tempdir().unwrap();
+
+    let home_dir = test_dir.path().join("home");
+    let sysa = test_dir.path().join("sys/a");
+    let sysb = test_dir.path().join("sys/b");
+
+    fs::create_dir_all(home_dir.clone()).unwrap();
+    fs::create_dir_all(sysa.clone()).unwrap();
+    fs::create_dir_all(sysb.clone()).unwrap();
+
+    unsafe { env::set_var("XDG_CONFIG_HOME", home_dir.clone()) };
+    unsafe {
+        env::set_var(
+            "XDG_CONFIG_DIRS",
+            format!("{0}:{1}", sysa.display(), sysb.display()),
+        )
+    };
+
+    let suffix = "xyz";
+    assert_eq!(None, xdg_existing_candidates(&dirs::CONFIG, suffix).next());
+
+    File::create(sysb.join(suffix)).unwrap();
+    File::create(home_dir.join(suffix)).unwrap();
+
+    assert_eq!(
+        Some(home_dir.join(suffix)),
+        xdg_existing_candidates(&dirs::CONFIG, suffix).next()
+    );
+    assert_eq!(
+        vec![home_dir.join(suffix), sysb.join(suffix)],
+        xdg_existing_candidates(&dirs::CONFIG, suffix).collect::<Vec<_>>()
+    );
+}
+
+#[cfg(all(feature = "fs", unix))]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_user_dir_same_as_system_dir() {
+    let test_dir = tempdir().unwrap();
+
+    let home_dir = test_dir.path().join("home");
+    let sys_dir = test_dir.path().join("sys");
+    let home_link = test_dir.path().join("link");
+
+    fs::create_dir_all(home_dir.clone()).unwrap();
+    fs::create_dir_all(sys_dir.clone()).unwrap();
+    std::os::unix::fs::symlink(home_dir.clone(), home_link.clone()).unwrap();
+
+    unsafe { env::set_var("XDG_CONFIG_HOME", home_dir.clone()) };
+    unsafe {
+        env::set_var(
+            "XDG_CONFIG_DIRS",
+            format!(
+                "{0}:{1}:{2}",
+                home_dir.display(),
+                home_link.display(),
+                sys_dir.display()
+            ),
+        )
+    };
+
+    let suffix = "xyz";
+    assert_eq!(
+        Err(Error::NotFound(
+            suffix.to_string(),
+            vec![
+                home_dir.join(suffix).to_string_lossy().to_string(),
+                sys_dir.join(suffix).to_string_lossy().to_string()
+            ]
+        )),
+        xdg_location_of(&dirs::CONFIG, suffix)
+    );
+
+    File::create(home_dir.join(suffix)).unwrap();
+    File::create(sys_dir.join(suffix)).unwrap();
+    assert_eq!(
+        vec![home_dir.join(suffix), sys_dir.join(suffix)],
+        xdg_existing_candidates(&dirs::CONFIG, suffix).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_xdg_candidate_paths() {
+    unsafe { env::set_var("XDG_DATA_HOME", "/some/path") };
+    unsafe { env::set_var("XDG_DATA_DIRS", "/some/other/path:/some/path") };
+    assert_eq!(
+        vec![
+            Path::new("/some/path/test"),
+            Path::new("/some/other/path/test")
+        ],
+        xdg_candidate_paths(&dirs::DATA, "test")
+    );
+
+    unsafe { env::remove_var("XDG_RUNTIME_DIR") };
+    assert!(xdg_candidate_paths(&dirs::RUNTIME, "test").is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_user_dir_os() {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    let suffix = OsStr::from_bytes(b"app/\xff\xfe");
+    let base = OsStr::from_bytes(b"/some/\xffpath");
+
+    unsafe { env::set_var("XDG_DATA_HOME", base) };
+    assert_eq!(
+        Path::new(base).join(suffix),
+        xdg_user_dir_os(&dirs::DATA, suffix).unwrap()
+    );
+
+    unsafe { env::remove_var("XDG_DATA_HOME") };
+    unsafe { env::set_var("HOME", "/some/home") };
+    assert_eq!(
+        Path::new("/some/home/.local/share").join(suffix),
+        xdg_user_dir_os(&dirs::DATA, suffix).unwrap()
+    );
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_xdg_single_instance_lock() {
+    let test_dir = tempdir().unwrap();
+    unsafe { env::set_var("XDG_STATE_HOME", test_dir.path()) };
+
+    let lock_path = test_dir.path().join("app/instance.lock");
+
+    let guard = xdg_single_instance_lock("app/instance").unwrap();
+    assert_eq!(lock_path, guard.path());
+    assert!(lock_path.exists());
+
+    assert_eq!(
+        Err(Error::AlreadyLocked(lock_path.clone())),
+        xdg_single_instance_lock("app/instance").map(|g| g.path().to_path_buf())
+    );
+
+    drop(guard);
+    assert_eq!(
+        lock_path,
+        xdg_single_instance_lock("app/instance").unwrap().path()
+    );
+}
+
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_xdg_location_of_with() {
+    use std::collections::HashSet;
+
+    unsafe { env::set_var("XDG_CONFIG_HOME", "/home") };
+    unsafe { env::set_var("XDG_CONFIG_DIRS", "/sys/a:/sys/b") };
+
+    let mut existing = HashSet::new();
+    let suffix = "xyz";
+
+    assert_eq!(
+        Err(Error::NotFound(
+            suffix.to_string(),
+            vec![
+                "/home/xyz".to_string(),
+                "/sys/a/xyz".to_string(),
+                "/sys/b/xyz".to_string()
+            ]
+        )),
+        xdg_location_of_with(&dirs::CONFIG, suffix, |p| existing.contains(p))
+    );
+
+    existing.insert(PathBuf::from("/sys/b/xyz"));
+    assert_eq!(
+        Path::new("/sys/b/xyz"),
+        xdg_location_of_with(&dirs::CONFIG, suffix, |p| existing.contains(p)).unwrap()
+    );
+
+    existing.insert(PathBuf::from("/home/xyz"));
+    assert_eq!(
+        Path::new("/home/xyz"),
+        xdg_location_of_with(&dirs::CONFIG, suffix, |p| existing.contains(p)).unwrap()
+    );
+}
+
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_sys_dirs_tagged() {
+    unsafe { env::set_var("XDG_DATA_DIRS", "/some/path") };
+    assert_eq!(
+        vec![(PathBuf::from("/some/path/test"), SysDirSource::EnvVar)],
+        xdg_system_dirs_tagged(&dirs::DATA, "test").unwrap()
+    );
+
+    unsafe { env::remove_var("XDG_DATA_DIRS") };
+    assert_eq!(
+        vec![
+            (
+                PathBuf::from("/usr/local/share/test"),
+                SysDirSource::Fallback
+            ),
+            (PathBuf::from("/usr/share/test"), SysDirSource::Fallback)
+        ],
+        xdg_system_dirs_tagged(&dirs::DATA, "test").unwrap()
+    );
+}
+
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_whitespace_env_var() {
+    unsafe { env::set_var("XDG_DATA_HOME", "   ") };
+    unsafe { env::set_var("HOME", "/some/home") };
+    assert_eq!(
+        xdg_user_dir(&dirs::DATA, "test").unwrap(),
+        Path::new("/some/home/.local/share/test")
+    );
+
+    unsafe { env::set_var("XDG_DATA_DIRS", " \t") };
+    assert_eq!(
+        xdg_system_dirs(&dirs::DATA, "test").unwrap(),
+        vec![
+            Path::new("/usr/local/share/test"),
+            Path::new("/usr/share/test")
+        ]
+    );
+}
+
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_context_etc_fallback() {
+    unsafe { env::set_var("XDG_CONFIG_DIRS", "/some/path") };
+
+    let ctx = XdgContext::new().with_etc_fallback(true);
+    assert_eq!(
+        vec![
+            (PathBuf::from("/some/path/app"), SysDirSource::EnvVar),
+            (PathBuf::from("/etc/app"), SysDirSource::Etc)
+        ],
+        ctx.system_dirs_tagged(&dirs::CONFIG, "app").unwrap()
+    );
+    assert_eq!(
+        vec![Path::new("/some/path/app")],
+        XdgContext::new().system_dirs(&dirs::CONFIG, "app").unwrap()
+    );
+
+    // Only applies to config
+    unsafe { env::set_var("XDG_DATA_DIRS", "/some/path") };
+    assert_eq!(
+        vec![Path::new("/some/path/app")],
+        ctx.system_dirs(&dirs::DATA, "app").unwrap()
+    );
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_xdg_first_creatable() {
+    let test_dir = tempdir().unwrap();
+    let sys_dir = test_dir.path().join("sys");
+    fs::create_dir_all(sys_dir.clone()).unwrap();
+
+    // Parent of the user dir is missing but can be created
+    let home_dir = test_dir.path().join("missing/home");
+    unsafe { env::set_var("XDG_CONFIG_HOME", home_dir.clone()) };
+    unsafe { env::set_var("XDG_CONFIG_DIRS", sys_dir.clone()) };
+    assert_eq!(
+        home_dir.join("app/config"),
+        xdg_first_creatable(&dirs::CONFIG, "app/config").unwrap()
+    );
+
+    // Parent of the user dir is under a file, so can never be created
+    let file = test_dir.path().join("file");
+    File::create(file.clone()).unwrap();
+    unsafe { env::set_var("XDG_CONFIG_HOME", file.join("home")) };
+    assert_eq!(
+        sys_dir.join("config"),
+        xdg_first_creatable(&dirs::CONFIG, "config").unwrap()
+    );
+
+    unsafe { env::set_var("XDG_CONFIG_DIRS", file.join("sys")) };
+    assert_eq!(
+        Err(Error::NotFound(
+            "config".to_string(),
+            vec![
+                file.join("home/config").to_string_lossy().to_string(),
+                file.join("sys/config").to_string_lossy().to_string()
+            ]
+        )),
+        xdg_first_creatable(&dirs::CONFIG, "config")
+    );
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_xdg_locale_conf() {
+    let test_dir = tempdir().unwrap();
+    unsafe { env::set_var("XDG_CONFIG_HOME", test_dir.path()) };
+    unsafe { env::set_var("XDG_CONFIG_DIRS", test_dir.path().join("sys")) };
+
+    fs::write(
+        test_dir.path().join("locale.conf"),
+        "# comment\n\nLANG=en_CA.UTF-8\n LC_TIME = \"en_GB.UTF-8\"\ninvalid\n",
+    )
+    .unwrap();
+
+    let locale = xdg_locale_conf().unwrap();
+    assert_eq!(2, locale.len());
+    assert_eq!("en_CA.UTF-8", locale["LANG"]);
+    assert_eq!("en_GB.UTF-8", locale["LC_TIME"]);
+}
+
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_context_tilde_expansion() {
+    unsafe { env::set_var("HOME", "/some/home") };
+    let ctx = XdgContext::new().with_tilde_expansion(true);
+
+    unsafe { env::set_var("XDG_CONFIG_HOME", "~") };
+    assert_eq!(
+        Path::new("/some/home/test"),
+        ctx.user_dir(&dirs::CONFIG, "test").unwrap()
+    );
+    assert_eq!(
+        Path::new("/some/home"),
+        ctx.user_dir(&dirs::CONFIG, "").unwrap()
+    );
+
+    unsafe { env::set_var("XDG_CONFIG_HOME", "~/") };
+    assert_eq!(
+        Path::new("/some/home/test"),
+        ctx.user_dir(&dirs::CONFIG, "test").unwrap()
+    );
+
+    unsafe { env::set_var("XDG_CONFIG_HOME", "~/subdir") };
+    assert_eq!(
+        Path::new("/some/home/subdir/test"),
+        ctx.user_dir(&dirs::CONFIG, "test").unwrap()
+    );
+
+    // Only a `~` component is expanded, and only when enabled. Otherwise the value is relative, so it is ignored.
+    unsafe { env::set_var("XDG_CONFIG_HOME", "~user/subdir") };
+    assert_eq!(
+        Path::new("/some/home/.config/test"),
+        ctx.user_dir(&dirs::CONFIG, "test").unwrap()
+    );
+    unsafe { env::set_var("XDG_CONFIG_HOME", "~/subdir") };
+    assert_eq!(
+        Path::new("/some/home/.config/test"),
+        xdg_user_dir(&dirs::CONFIG, "test").unwrap()
+    );
+}
+
+#[test]
+fn test_diff_resolution() {
+    use std::collections::HashMap;
+
+    let a = HashMap::from([("HOME", "/home/a"), ("XDG_CONFIG_DIRS", "/etc/xdg")]);
+    let b = HashMap::from([("HOME", "/home/b"), ("XDG_CONFIG_DIRS", "/opt/xdg")]);
+    let diff = diff_resolution(a.clone(), b, &dirs::CONFIG, "app");
+    assert!(!diff.is_same());
+    assert_eq!(diff.user_a, Ok(PathBuf::from("/home/a/.config/app")));
+    assert_eq!(diff.user_b, Ok(PathBuf::from("/home/b/.config/app")));
+    assert_eq!(
+        diff.reasons,
+        vec![
+            DiffReason::EnvVar("HOME"),
+            DiffReason::EnvVar("XDG_CONFIG_DIRS")
+        ]
+    );
+
+    // Explicitly setting the default system dirs gives the same paths, but from a different source
+    let c = HashMap::from([("HOME", "/home/a"), ("XDG_CONFIG_HOME", "/home/a/.config")]);
+    let diff = diff_resolution(a.clone(), c, &dirs::CONFIG, "app");
+    assert_eq!(diff.user_a, diff.user_b);
+    assert_ne!(diff.system_a, diff.system_b);
+    assert_eq!(diff.reasons, vec![DiffReason::Fallback("XDG_CONFIG_DIRS")]);
+
+    assert!(diff_resolution(a.clone(), a, &dirs::CONFIG, "app").is_same());
+}
+
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_resource_search_path() {
+    unsafe {
+        env::set_var("HOME", "/home/user");
+        env::remove_var("XDG_CONFIG_HOME");
+        env::remove_var("XDG_CONFIG_DIRS");
+        env::set_var("XDG_DATA_HOME", "/data");
+        env::set_var("XDG_DATA_DIRS", "/usr/share");
+    }
+
+    assert_eq!(
+        xdg_resource_search_path(ResourceType::Autostart),
+        vec![
+            PathBuf::from("/home/user/.config/autostart"),
+            PathBuf::from("/etc/xdg/autostart"),
+        ]
+    );
+    assert_eq!(
+        xdg_resource_search_path(ResourceType::Icons),
+        vec![
+            PathBuf::from("/home/user/.icons"),
+            PathBuf::from("/data/icons"),
+            PathBuf::from("/usr/share/icons"),
+            PathBuf::from("/usr/share/pixmaps"),
+        ]
+    );
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_create_dir_and_write() {
+    let test_dir = tempdir().unwrap();
+    unsafe { env::set_var("XDG_CONFIG_HOME", test_dir.path()) };
+
+    let dir = xdg_create_dir(&dirs::CONFIG, "app/nested").unwrap();
+    assert!(dir.is_dir());
+
+    let file = xdg_write(&dirs::CONFIG, "other/config.toml", "key = 1").unwrap();
+    assert_eq!(file, test_dir.path().join("other/config.toml"));
+    assert_eq!(fs::read_to_string(&file).unwrap(), "key = 1");
+}
+
+#[cfg(all(feature = "fs", unix))]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_context_secure_mode() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let test_dir = tempdir().unwrap();
+    unsafe { env::set_var("XDG_CONFIG_HOME", test_dir.path()) };
+    let ctx = XdgContext::new().secure_mode();
+
+    let file = ctx.write(&dirs::CONFIG, "app/secret", "hunter2").unwrap();
+    let mode = |p: &Path| fs::metadata(p).unwrap().permissions().mode() & 0o777;
+    assert_eq!(mode(file.parent().unwrap()), 0o700);
+    assert_eq!(mode(&file), 0o600);
+
+    let dir = ctx.create_dir(&dirs::CONFIG, "app/keys").unwrap();
+    assert_eq!(mode(&dir), 0o700);
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_config_source() {
+    let test_dir = tempdir().unwrap();
+    unsafe {
+        env::set_var("XDG_CONFIG_HOME", test_dir.path());
+        env::set_var("XDG_CONFIG_DIRS", test_dir.path().join("system"));
+    }
+
+    assert_eq!(
+        xdg_config_source("app.toml", b"default"),
+        ConfigSource::Embedded(b"default")
+    );
+
+    File::create(test_dir.path().join("app.toml")).unwrap();
+    assert_eq!(
+        xdg_config_source("app.toml", b"default"),
+        ConfigSource::OnDisk(test_dir.path().join("app.toml").canonicalize().unwrap())
+    );
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_installed_app_ids() {
+    let test_dir = tempdir().unwrap();
+    let user = test_dir.path().join("user");
+    let system = test_dir.path().join("system");
+    fs::create_dir_all(user.join("b-app/nested")).unwrap();
+    fs::create_dir_all(user.join("a-app")).unwrap();
+    fs::create_dir_all(system.join("a-app")).unwrap();
+    fs::create_dir_all(system.join("c-app")).unwrap();
+    File::create(user.join("not-an-app.conf")).unwrap();
+    unsafe {
+        env::set_var("XDG_CONFIG_HOME", &user);
+        env::set_var("XDG_CONFIG_DIRS", &system);
+    }
+
+    assert_eq!(
+        xdg_installed_app_ids(&dirs::CONFIG, false),
+        vec!["a-app", "b-app"]
+    );
+    assert_eq!(
+        xdg_installed_app_ids(&dirs::CONFIG, true),
+        vec!["a-app", "b-app", "c-app"]
+    );
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_keyed_merge() {
+    let test_dir = tempdir().unwrap();
+    let user = test_dir.path().join("user");
+    let sys1 = test_dir.path().join("sys1");
+    let sys2 = test_dir.path().join("sys2");
+    for dir in [&user, &sys1, &sys2] {
+        fs::create_dir_all(dir).unwrap();
+    }
+    fs::write(user.join("app.list"), "a=user\n").unwrap();
+    fs::write(sys1.join("app.list"), "a=sys1\nb=sys1\n").unwrap();
+    fs::write(sys2.join("app.list"), "b=sys2\nc=sys2\n").unwrap();
+    unsafe {
+        env::set_var("XDG_CONFIG_HOME", &user);
+        env::set_var(
+            "XDG_CONFIG_DIRS",
+            format!("{}:{}", sys1.display(), sys2.display()),
+        );
+    }
+
+    let parse = |s: &str| {
+        s.lines()
+            .filter_map(|l| l.split_once('='))
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    };
+    let merged = xdg_keyed_merge("app.list", parse);
+    assert_eq!(merged.len(), 3);
+    assert_eq!(merged["a"], "user");
+    assert_eq!(merged["b"], "sys1");
+    assert_eq!(merged["c"], "sys2");
+}
+
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_xdg_env() {
+    unsafe {
+        env::set_var("HOME", "/home/process");
+        env::set_var("XDG_DATA_DIRS", "/process/share");
+    }
+
+    let xdg_env = XdgEnv {
+        home: Some("/home/user".into()),
+        cache_home: Some("/cache".into()),
+        config_dirs: Some("/a:/b".into()),
+        ..Default::default()
+    };
+    assert_eq!(
+        xdg_env.resolve_user(&dirs::CONFIG, "app"),
+        Ok(PathBuf::from("/home/user/.config/app"))
+    );
+    assert_eq!(
+        xdg_env.resolve_user(&dirs::CACHE, "app"),
+        Ok(PathBuf::from("/cache/app"))
+    );
+    assert_eq!(
+        xdg_env.resolve_user(&dirs::RUNTIME, "app"),
+        Err(Error::EnvVarNotSet("XDG_RUNTIME_DIR"))
+    );
+    assert_eq!(
+        xdg_env.resolve_system(&dirs::CONFIG, "app"),
+        Ok(vec![PathBuf::from("/a/app"), PathBuf::from("/b/app")])
+    );
+    // Unset here, so uses the default rather than the process environment
+    assert_eq!(
+        xdg_env.resolve_system(&dirs::DATA, "app"),
+        Ok(vec![
+            PathBuf::from("/usr/local/share/app"),
+            PathBuf::from("/usr/share/app")
+        ])
+    );
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_locate_full() {
+    let test_dir = tempdir().unwrap();
+    let user = test_dir.path().join("user");
+    let system = test_dir.path().join("system");
+    fs::create_dir_all(&system).unwrap();
+    unsafe {
+        env::set_var("XDG_DATA_HOME", &user);
+        env::set_var("XDG_DATA_DIRS", &system);
+    }
+
+    let located = xdg_locate_full(&dirs::DATA, "app");
+    assert_eq!(located.found, None);
+    assert_eq!(located.origin, None);
+    assert_eq!(located.searched, vec![user.join("app"), system.join("app")]);
+
+    fs::create_dir(system.join("app")).unwrap();
+    let located = xdg_locate_full(&dirs::DATA, "app");
+    assert_eq!(
+        located.found,
+        Some(system.join("app").canonicalize().unwrap())
+    );
+    assert_eq!(located.origin, Some(Origin::System(SysDirSource::EnvVar)));
+    assert_eq!(located.searched, vec![user.join("app"), system.join("app")]);
+}
+
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_context_strict_empty_dirs() {
+    let strict = XdgContext::new().with_strict_empty_dirs(true);
+    let lenient = XdgContext::new();
+    let default = vec![PathBuf::from("/etc/xdg/app")];
+
+    // Unset: both use the default
+    unsafe { env::remove_var("XDG_CONFIG_DIRS") };
+    assert_eq!(
+        strict.system_dirs(&dirs::CONFIG, "app"),
+        Ok(default.clone())
+    );
+    assert_eq!(
+        lenient.system_dirs(&dirs::CONFIG, "app"),
+        Ok(default.clone())
+    );
+
+    // Set but empty: only strict mode gives no paths
+    unsafe { env::set_var("XDG_CONFIG_DIRS", "") };
+    assert_eq!(strict.system_dirs(&dirs::CONFIG, "app"), Ok(vec![]));
+    assert_eq!(lenient.system_dirs(&dirs::CONFIG, "app"), Ok(default));
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_location_of_with_extra() {
+    let test_dir = tempdir().unwrap();
+    let user = test_dir.path().join("user");
+    let system = test_dir.path().join("system");
+    let extra = [
+        test_dir.path().join("extra1"),
+        test_dir.path().join("extra2"),
+    ];
+    unsafe {
+        env::set_var("XDG_DATA_HOME", &user);
+        env::set_var("XDG_DATA_DIRS", &system);
+    }
+
+    let checked = [&user, &system, &extra[0], &extra[1]]
+        .map(|dir| dir.join("plugin").to_string_lossy().to_string())
+        .to_vec();
+    assert_eq!(
+        xdg_location_of_with_extra(&dirs::DATA, "plugin", &extra),
+        Err(Error::NotFound("plugin".to_string(), checked))
+    );
+
+    fs::create_dir_all(extra[1].join("plugin")).unwrap();
+    assert_eq!(
+        xdg_location_of_with_extra(&dirs::DATA, "plugin", &extra),
+        Ok(extra[1].join("plugin").canonicalize().unwrap())
+    );
+
+    // The suffix stays under the extra directories, without redundant separators
+    let checked = [&user, &system, &extra[0], &extra[1]]
+        .map(|dir| dir.to_string_lossy().to_string())
+        .to_vec();
+    fs::remove_dir_all(&extra[1]).unwrap();
+    assert_eq!(
+        xdg_location_of_with_extra(&dirs::DATA, "", &extra),
+        Err(Error::NotFound(String::new(), checked))
+    );
+    assert_eq!(
+        xdg_location_of_with_extra(&dirs::DATA, "/zzz", &extra),
+        Err(Error::NotFound(
+            "/zzz".to_string(),
+            [&user, &system, &extra[0], &extra[1]]
+                .map(|dir| dir.join("zzz").to_string_lossy().to_string())
+                .to_vec()
+        ))
+    );
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_ensure_config_file() {
+    let test_dir = tempdir().unwrap();
+    unsafe { env::set_var("XDG_CONFIG_HOME", test_dir.path()) };
+    let expected = test_dir.path().join("app/config.toml");
+
+    assert_eq!(
+        xdg_ensure_config_file("app/config.toml", b"default"),
+        Ok(expected.clone())
+    );
+    assert_eq!(fs::read_to_string(&expected).unwrap(), "default");
+
+    // Existing contents are kept
+    fs::write(&expected, "edited").unwrap();
+    assert_eq!(
+        xdg_ensure_config_file("app/config.toml", b"default"),
+        Ok(expected.clone())
+    );
+    assert_eq!(fs::read_to_string(&expected).unwrap(), "edited");
+}
+
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_context_cache() {
+    unsafe { env::set_var("XDG_CACHE_HOME", "/cache1") };
+    let ctx = XdgContext::new().with_cache();
+    assert_eq!(
+        ctx.user_dir(&dirs::CACHE, "app"),
+        Ok(PathBuf::from("/cache1/app"))
+    );
+
+    // Still the cached path until invalidated
+    unsafe { env::set_var("XDG_CACHE_HOME", "/cache2") };
+    assert_eq!(
+        ctx.user_dir(&dirs::CACHE, "app"),
+        Ok(PathBuf::from("/cache1/app"))
+    );
+    assert_eq!(
+        ctx.user_dir(&dirs::CACHE, "other"),
+        Ok(PathBuf::from("/cache2/other"))
+    );
+
+    ctx.invalidate();
+    assert_eq!(
+        ctx.user_dir(&dirs::CACHE, "app"),
+        Ok(PathBuf::from("/cache2/app"))
+    );
+
+    // Basedirs sharing a variable but with different defaults are cached separately, as are differently configured
+    // clones sharing the cache
+    const FIRST: XdgDir = XdgDir::new("MYAPP_HOME", Some(".first"), None, None);
+    const SECOND: XdgDir = XdgDir::new("MYAPP_HOME", Some(".second"), None, None);
+    unsafe {
+        env::set_var("HOME", "/home/user");
+        env::remove_var("MYAPP_HOME");
+    }
+    assert_eq!(
+        ctx.user_dir(&FIRST, "app"),
+        Ok(PathBuf::from("/home/user/.first/app"))
+    );
+    assert_eq!(
+        ctx.user_dir(&SECOND, "app"),
+        Ok(PathBuf::from("/home/user/.second/app"))
+    );
+    assert_eq!(
+        ctx.clone().no_home_fallback().user_dir(&FIRST, "app"),
+        Err(Error::EnvVarNotSet("MYAPP_HOME"))
+    );
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_orphaned_cache_dirs() {
+    let test_dir = tempdir().unwrap();
+    let cache = test_dir.path().join("cache");
+    let config = test_dir.path().join("config");
+    let system = test_dir.path().join("system");
+    for dir in [
+        "cache/kept",
+        "cache/system-kept",
+        "cache/orphan",
+        "config/kept",
+        "system/system-kept",
+    ] {
+        fs::create_dir_all(test_dir.path().join(dir)).unwrap();
+    }
+    unsafe {
+        env::set_var("XDG_CACHE_HOME", &cache);
+        env::set_var("XDG_CONFIG_HOME", &config);
+        env::set_var("XDG_CONFIG_DIRS", &system);
+    }
+
+    assert_eq!(orphaned_cache_dirs(), vec![cache.join("orphan")]);
+}
+
+#[test]
+fn test_context_fs_root() {
+    let target_env = XdgEnv {
+        home: Some("/home/other".into()),
+        data_dirs: Some("/usr/share".into()),
+        ..Default::default()
+    };
+    let ctx = XdgContext::new()
+        .with_env(target_env)
+        .with_fs_root(PathBuf::from("/mnt/root"));
+
+    assert_eq!(
+        ctx.user_dir(&dirs::DATA, "app"),
+        Ok(PathBuf::from("/mnt/root/home/other/.local/share/app"))
+    );
+    assert_eq!(
+        ctx.system_dirs(&dirs::DATA, "app"),
+        Ok(vec![PathBuf::from("/mnt/root/usr/share/app")])
+    );
+}
+
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_try_each() {
+    unsafe {
+        env::set_var("XDG_DATA_HOME", "/user");
+        env::set_var("XDG_DATA_DIRS", "/sys1:/sys2");
+    }
+
+    let mut tried = vec![];
+    let found = xdg_try_each(&dirs::DATA, "app", |p| {
+        tried.push(p.to_path_buf());
+        p.starts_with("/sys1").then(|| p.to_string_lossy().len())
+    });
+    assert_eq!(found, Some("/sys1/app".len()));
+    assert_eq!(
+        tried,
+        vec![PathBuf::from("/user/app"), PathBuf::from("/sys1/app")]
+    );
+
+    assert_eq!(xdg_try_each(&dirs::DATA, "app", |_| None::<()>), None);
+}
+
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_versioned_config() {
+    unsafe { env::set_var("XDG_CONFIG_HOME", "/config") };
+    assert_eq!(
+        xdg_versioned_config("app", 2, "config.toml"),
+        Ok(PathBuf::from("/config/app/v2/config.toml"))
+    );
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_latest_config_version() {
+    let test_dir = tempdir().unwrap();
+    unsafe { env::set_var("XDG_CONFIG_HOME", test_dir.path()) };
+    assert_eq!(xdg_latest_config_version("app"), None);
+
+    for dir in ["app/v1", "app/v10", "app/v2", "app/vnext"] {
+        fs::create_dir_all(test_dir.path().join(dir)).unwrap();
+    }
+    File::create(test_dir.path().join("app/v20")).unwrap();
+    assert_eq!(xdg_latest_config_version("app"), Some(10));
+}
+
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_debug_dump() {
+    // The dump goes to stderr, this only checks that it does not affect resolution. Its contents, and that it is only
+    // written once, are tested along with the dump itself
+    unsafe {
+        env::set_var("XDG_DIRS_DEBUG", "1");
+        env::set_var("XDG_STATE_HOME", "/state");
+    }
+    assert_eq!(
+        xdg_user_dir(&dirs::STATE, "app"),
+        Ok(PathBuf::from("/state/app"))
+    );
+    assert_eq!(
+        xdg_user_dir(&dirs::STATE, "app"),
+        Ok(PathBuf::from("/state/app"))
+    );
+    unsafe { env::remove_var("XDG_DIRS_DEBUG") };
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_find_in_all_bases() {
+    let test_dir = tempdir().unwrap();
+    let root = test_dir.path().canonicalize().unwrap();
+    for dir in ["config/app", "data/app", "data-sys/app", "cache"] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    unsafe {
+        env::set_var("XDG_CONFIG_HOME", root.join("config"));
+        env::set_var("XDG_CONFIG_DIRS", root.join("config-sys"));
+        env::set_var("XDG_DATA_HOME", root.join("data"));
+        env::set_var("XDG_DATA_DIRS", root.join("data-sys"));
+        env::set_var("XDG_CACHE_HOME", root.join("cache"));
+        env::set_var("XDG_STATE_HOME", root.join("state"));
+        env::set_var("XDG_RUNTIME_DIR", root.join("runtime"));
+    }
+
+    let found: Vec<_> = xdg_find_in_all_bases("app")
+        .into_iter()
+        .map(|(xdg_dir, path)| (xdg_dir.schema("").user_template, path))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            ("$XDG_CONFIG_HOME".to_string(), root.join("config/app")),
+            ("$XDG_DATA_HOME".to_string(), root.join("data/app")),
+            ("$XDG_DATA_HOME".to_string(), root.join("data-sys/app")),
+        ]
+    );
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_wayland_socket() {
+    let test_dir = tempdir().unwrap();
+    unsafe {
+        env::set_var("XDG_RUNTIME_DIR", test_dir.path());
+        env::remove_var("WAYLAND_DISPLAY");
+    }
+    assert_eq!(wayland_socket(None), None);
+
+    File::create(test_dir.path().join("wayland-0")).unwrap();
+    File::create(test_dir.path().join("wayland-1")).unwrap();
+    assert_eq!(
+        wayland_socket(None),
+        Some(test_dir.path().join("wayland-0"))
+    );
+    assert_eq!(
+        wayland_socket(Some("wayland-1")),
+        Some(test_dir.path().join("wayland-1"))
+    );
+    assert_eq!(wayland_socket(Some("wayland-2")), None);
+
+    unsafe { env::set_var("WAYLAND_DISPLAY", "wayland-1") };
+    assert_eq!(
+        wayland_socket(None),
+        Some(test_dir.path().join("wayland-1"))
+    );
+    unsafe { env::remove_var("WAYLAND_DISPLAY") };
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_x11_socket() {
+    // Only checks the cases that don't depend on a running X server
+    unsafe { env::set_var("DISPLAY", "remote.example.com:0") };
+    assert_eq!(x11_socket(), None);
+    unsafe { env::set_var("DISPLAY", "not a display") };
+    assert_eq!(x11_socket(), None);
+    unsafe { env::remove_var("DISPLAY") };
+    assert_eq!(x11_socket(), None);
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_app_disk_usage() {
+    let test_dir = tempdir().unwrap();
+    for (var, dir) in [
+        ("XDG_CONFIG_HOME", "config"),
+        ("XDG_DATA_HOME", "data"),
+        ("XDG_CACHE_HOME", "cache"),
+        ("XDG_STATE_HOME", "state"),
+    ] {
+        unsafe { env::set_var(var, test_dir.path().join(dir)) };
+    }
+    fs::create_dir_all(test_dir.path().join("config/app")).unwrap();
+    fs::create_dir_all(test_dir.path().join("cache/app/nested")).unwrap();
+    fs::write(test_dir.path().join("config/app/config.toml"), [0; 10]).unwrap();
+    fs::write(test_dir.path().join("cache/app/a"), [0; 100]).unwrap();
+    fs::write(test_dir.path().join("cache/app/nested/b"), [0; 1000]).unwrap();
+
+    let usage = xdg_app_disk_usage("app").unwrap();
+    assert_eq!(
+        usage,
+        AppUsage {
+            config: 10,
+            data: 0,
+            cache: 1100,
+            state: 0
+        }
+    );
+    assert_eq!(usage.total(), 1110);
+}
+
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_base_dir_wrappers() {
+    unsafe {
+        env::set_var("XDG_DATA_HOME", "/data");
+        env::set_var("XDG_CACHE_HOME", "/cache");
+        env::set_var("XDG_STATE_HOME", "/state");
+        env::set_var("XDG_RUNTIME_DIR", "/run/user/1000");
+    }
+    assert_eq!(xdg_data_dir("app"), Ok(PathBuf::from("/data/app")));
+    assert_eq!(xdg_cache_dir("app"), Ok(PathBuf::from("/cache/app")));
+    assert_eq!(xdg_state_dir("app"), Ok(PathBuf::from("/state/app")));
+    assert_eq!(
+        xdg_runtime_dir("app"),
+        Ok(PathBuf::from("/run/user/1000/app"))
+    );
+
+    unsafe { env::remove_var("XDG_RUNTIME_DIR") };
+    assert_eq!(
+        xdg_runtime_dir("app"),
+        Err(Error::EnvVarNotSet("XDG_RUNTIME_DIR"))
+    );
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_context_clock() {
+    use std::time::{Duration, SystemTime};
+
+    let test_dir = tempdir().unwrap();
+    let user = test_dir.path().join("user");
+    let system = test_dir.path().join("system");
+    fs::create_dir_all(&user).unwrap();
+    fs::create_dir_all(&system).unwrap();
+    unsafe {
+        env::set_var("XDG_CACHE_HOME", &user);
+        env::set_var("XDG_DATA_HOME", &user);
+        env::set_var("XDG_DATA_DIRS", &system);
+    }
+
+    let epoch = SystemTime::UNIX_EPOCH;
+    let hour = Duration::from_secs(3600);
+    File::create(user.join("file"))
+        .unwrap()
+        .set_modified(epoch)
+        .unwrap();
+    File::create(system.join("file"))
+        .unwrap()
+        .set_modified(epoch + hour)
+        .unwrap();
+
+    let ctx = XdgContext::new().with_clock(move || epoch + 2 * hour);
+    assert!(ctx.cache_fresh("file", 2 * hour));
+    assert!(!ctx.cache_fresh("file", hour));
+    assert!(!ctx.cache_fresh("missing", hour));
+
+    assert_eq!(
+        ctx.locations_by_mtime(&dirs::DATA, "file"),
+        vec![(system.join("file"), hour), (user.join("file"), 2 * hour)]
+    );
+
+    // Real clock
+    assert!(!xdg_cache_fresh("file", hour));
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_portal_config() {
+    let test_dir = tempdir().unwrap();
+    let root = test_dir.path().canonicalize().unwrap();
+    unsafe {
+        env::set_var("XDG_CONFIG_HOME", root.join("config"));
+        env::set_var("XDG_CONFIG_DIRS", root.join("config-sys"));
+        env::set_var("XDG_DATA_HOME", root.join("data"));
+        env::set_var("XDG_DATA_DIRS", root.join("data-sys"));
+    }
+
+    assert_eq!(
+        portal_config_dirs(),
+        vec![
+            root.join("config/xdg-desktop-portal"),
+            root.join("config-sys/xdg-desktop-portal"),
+            PathBuf::from("/etc/xdg-desktop-portal"),
+            root.join("data/xdg-desktop-portal"),
+            root.join("data-sys/xdg-desktop-portal"),
+        ]
+    );
+
+    assert_eq!(portal_config_file("test-portals.conf"), None);
+    fs::create_dir_all(root.join("data-sys/xdg-desktop-portal")).unwrap();
+    File::create(root.join("data-sys/xdg-desktop-portal/test-portals.conf")).unwrap();
+    assert_eq!(
+        portal_config_file("test-portals.conf"),
+        Some(root.join("data-sys/xdg-desktop-portal/test-portals.conf"))
+    );
+    // An absolute name is still looked up in the portal dirs
+    assert_eq!(
+        portal_config_file("/test-portals.conf"),
+        Some(root.join("data-sys/xdg-desktop-portal/test-portals.conf"))
+    );
+}
+
+#[test]
+fn test_xdg_dir_accessors() {
+    assert_eq!(dirs::CONFIG.env_var(), "XDG_CONFIG_HOME");
+    assert_eq!(dirs::CONFIG.home_fallback(), Some(".config"));
+    assert_eq!(dirs::CONFIG.system_var(), Some("XDG_CONFIG_DIRS"));
+    assert_eq!(dirs::CONFIG.system_fallback(), Some(&["/etc/xdg"][..]));
+
+    assert_eq!(dirs::RUNTIME.env_var(), "XDG_RUNTIME_DIR");
+    assert_eq!(dirs::RUNTIME.home_fallback(), None);
+    assert_eq!(dirs::RUNTIME.system_var(), None);
+    assert_eq!(dirs::RUNTIME.system_fallback(), None);
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_write_atomic() {
+    let test_dir = tempdir().unwrap();
+    unsafe { env::set_var("XDG_STATE_HOME", test_dir.path()) };
+
+    let path = xdg_write_atomic(&dirs::STATE, "app/state.json", "{}").unwrap();
+    assert_eq!(path, test_dir.path().join("app/state.json"));
+    assert_eq!(fs::read_to_string(&path).unwrap(), "{}");
+
+    xdg_write_atomic(&dirs::STATE, "app/state.json", "{\"a\": 1}").unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "{\"a\": 1}");
+    // No temporary files left behind
+    assert_eq!(
+        fs::read_dir(test_dir.path().join("app")).unwrap().count(),
+        1
+    );
+}
+
+#[cfg(feature = "fs")]
+#[test]
+fn test_same_filesystem() {
+    let test_dir = tempdir().unwrap();
+    let file = test_dir.path().join("file");
+    File::create(&file).unwrap();
+
+    assert_eq!(same_filesystem(test_dir.path(), &file).ok(), Some(true));
+    assert!(same_filesystem(test_dir.path(), &test_dir.path().join("missing")).is_err());
+}
+
+const MYAPP_CONFIG: XdgDir = XdgDir::new(
+    "MYAPP_CONFIG_HOME",
+    Some(".myapp/"),
+    Some("MYAPP_CONFIG_DIRS"),
+    Some(&["/etc/myapp"]),
+)
+.with_description("myapp configuration");
+
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_custom_xdg_dir() {
+    unsafe {
+        env::set_var("HOME", "/home/user");
+        env::remove_var("MYAPP_CONFIG_HOME");
+        env::set_var("MYAPP_CONFIG_DIRS", "/a:/b");
+    }
+
+    assert_eq!(
+        xdg_user_dir(&MYAPP_CONFIG, "config"),
+        Ok(PathBuf::from("/home/user/.myapp/config"))
+    );
+    assert_eq!(
+        xdg_system_dirs(&MYAPP_CONFIG, "config"),
+        Ok(vec![PathBuf::from("/a/config"), PathBuf::from("/b/config")])
+    );
+
+    unsafe { env::remove_var("MYAPP_CONFIG_DIRS") };
+    assert_eq!(
+        xdg_system_dirs(&MYAPP_CONFIG, "config"),
+        Ok(vec![PathBuf::from("/etc/myapp/config")])
+    );
+
+    const NO_SYSTEM: XdgDir = XdgDir::new("MYAPP_RUNTIME", None, None, None);
+    assert_eq!(
+        xdg_system_dirs(&NO_SYSTEM, "x"),
+        Err(Error::SystemDirNotApplicable("MYAPP_RUNTIME"))
+    );
+}
+
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_search_paths() {
+    unsafe {
+        env::set_var("XDG_CONFIG_HOME", "/user");
+        env::set_var("XDG_CONFIG_DIRS", "/sys1:/sys2");
+        env::remove_var("XDG_RUNTIME_DIR");
+    }
+
+    assert_eq!(
+        xdg_search_paths(&dirs::CONFIG, "app"),
+        Ok(vec![
+            PathBuf::from("/user/app"),
+            PathBuf::from("/sys1/app"),
+            PathBuf::from("/sys2/app"),
+        ])
+    );
+    assert_eq!(
+        xdg_search_paths(&dirs::RUNTIME, "app"),
+        Err(Error::EnvVarNotSet("XDG_RUNTIME_DIR"))
+    );
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_runtime_dir_lenient() {
+    unsafe { env::set_var("XDG_RUNTIME_DIR", "/run/user/1000") };
+    assert_eq!(
+        xdg_runtime_dir_lenient("app"),
+        (Some(PathBuf::from("/run/user/1000/app")), vec![])
+    );
+
+    unsafe { env::remove_var("XDG_RUNTIME_DIR") };
+    let (path, warnings) = xdg_runtime_dir_lenient("app");
+    assert_eq!(warnings[0], Warning::EnvVarNotSet("XDG_RUNTIME_DIR"));
+    // Whether there is a substitute depends on the system running the test
+    match path {
+        Some(path) => {
+            let Warning::Substituted(substitute) = &warnings[1] else {
+                panic!("expected a substitution warning: {warnings:?}");
+            };
+            assert_eq!(path, substitute.join("app"));
+            // The suffix is normalized the same way as for any other path
+            assert_eq!(xdg_runtime_dir_lenient("/app/").0, Some(path));
+            assert_eq!(
+                xdg_runtime_dir_lenient("").0.unwrap().as_os_str(),
+                substitute.as_os_str()
+            );
+        }
+        None => assert_eq!(warnings.len(), 1),
+    }
+}
+
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_profiled_config() {
+    unsafe {
+        env::set_var("XDG_CONFIG_HOME", "/config");
+        env::remove_var("MYAPP_PROFILE");
+    }
+    assert_eq!(
+        xdg_profiled_config("MYAPP_PROFILE", "myapp", "config.toml"),
+        Ok(PathBuf::from("/config/myapp/config.toml"))
+    );
+
+    unsafe { env::set_var("MYAPP_PROFILE", "work") };
+    assert_eq!(
+        xdg_profiled_config("MYAPP_PROFILE", "myapp", "config.toml"),
+        Ok(PathBuf::from("/config/myapp/work/config.toml"))
+    );
+
+    // A profile that would escape the base directory is ignored
+    for profile in ["../../.ssh", "..", "a/b"] {
+        unsafe { env::set_var("MYAPP_PROFILE", profile) };
+        assert_eq!(
+            xdg_profiled_config("MYAPP_PROFILE", "myapp", "config.toml"),
+            Ok(PathBuf::from("/config/myapp/config.toml"))
+        );
+    }
+    unsafe { env::remove_var("MYAPP_PROFILE") };
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_location_of_kind() {
+    let test_dir = tempdir().unwrap();
+    let root = test_dir.path().canonicalize().unwrap();
+    fs::create_dir_all(root.join("user/app.conf")).unwrap();
+    fs::create_dir_all(root.join("system")).unwrap();
+    File::create(root.join("system/app.conf")).unwrap();
+    unsafe {
+        env::set_var("XDG_CONFIG_HOME", root.join("user"));
+        env::set_var("XDG_CONFIG_DIRS", root.join("system"));
+    }
+
+    assert_eq!(
+        xdg_location_of_kind(&dirs::CONFIG, "app.conf", EntryKind::Any),
+        Ok(root.join("user/app.conf"))
+    );
+    assert_eq!(
+        xdg_location_of_kind(&dirs::CONFIG, "app.conf", EntryKind::Dir),
+        Ok(root.join("user/app.conf"))
+    );
+    assert_eq!(
+        xdg_location_of_kind(&dirs::CONFIG, "app.conf", EntryKind::File),
+        Ok(root.join("system/app.conf"))
+    );
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_find_by_content() {
+    let test_dir = tempdir().unwrap();
+    let user = test_dir.path().join("user/app");
+    let system = test_dir.path().join("system/app");
+    fs::create_dir_all(&user).unwrap();
+    fs::create_dir_all(&system).unwrap();
+    fs::write(user.join("a.conf"), "#!other\n").unwrap();
+    fs::write(system.join("b.conf"), "#!myapp\n").unwrap();
+    fs::write(system.join("c.conf"), "#!myapp\n").unwrap();
+    // Longer than the prefix that is read, so the marker at the end is never seen
+    let mut big = vec![b' '; CONTENT_PREFIX_LEN];
+    big.extend_from_slice(b"#!myapp");
+    fs::write(user.join("big.conf"), big).unwrap();
+    unsafe {
+        env::set_var("XDG_CONFIG_HOME", test_dir.path().join("user"));
+        env::set_var("XDG_CONFIG_DIRS", test_dir.path().join("system"));
+    }
+
+    let is_myapp = |prefix: &[u8]| prefix.windows(7).any(|w| w == b"#!myapp");
+    assert_eq!(
+        xdg_find_by_content(&dirs::CONFIG, "app", is_myapp),
+        Some(system.join("b.conf"))
+    );
+    assert_eq!(xdg_find_by_content(&dirs::CONFIG, "app", |_| false), None);
+}
+
+#[cfg(all(feature = "fs", unix))]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_all_locations_of() {
+    let test_dir = tempdir().unwrap();
+    let root = test_dir.path().canonicalize().unwrap();
+    for dir in ["user", "sys1", "sys2"] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    std::os::unix::fs::symlink(root.join("sys1"), root.join("sys1-link")).unwrap();
+    unsafe {
+        env::set_var("XDG_DATA_HOME", root.join("user"));
+        env::set_var(
+            "XDG_DATA_DIRS",
+            format!("{0}/sys1:{0}/sys1-link:{0}/sys2", root.display()),
+        );
+        env::remove_var("XDG_RUNTIME_DIR");
+    }
+
+    assert_eq!(xdg_all_locations_of(&dirs::DATA, "app.conf"), Ok(vec![]));
+
+    File::create(root.join("user/app.conf")).unwrap();
+    File::create(root.join("sys1/app.conf")).unwrap();
+    File::create(root.join("sys2/app.conf")).unwrap();
+    assert_eq!(
+        xdg_all_locations_of(&dirs::DATA, "app.conf"),
+        Ok(vec![
+            root.join("user/app.conf"),
+            root.join("sys1/app.conf"),
+            root.join("sys2/app.conf"),
+        ])
+    );
+
+    assert_eq!(
+        xdg_all_locations_of(&dirs::RUNTIME, "app"),
+        Err(Error::EnvVarNotSet("XDG_RUNTIME_DIR"))
+    );
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_resolved_dir_as_path() {
+    let test_dir = tempdir().unwrap();
+    unsafe { env::set_var("XDG_CONFIG_HOME", test_dir.path()) };
+    assert_eq!(xdg_locate_full(&dirs::CONFIG, "app.conf").resolved(), None);
+
+    fs::write(test_dir.path().join("app.conf"), "contents").unwrap();
+    let resolved = xdg_locate_full(&dirs::CONFIG, "app.conf")
+        .resolved()
+        .unwrap();
+    assert_eq!(resolved.origin, Origin::User);
+
+    // Usable as a path through both `AsRef<Path>` and `Deref`
+    File::open(&resolved).unwrap();
+    assert_eq!(fs::read_to_string(&*resolved).unwrap(), "contents");
+    assert_eq!(resolved.file_name(), Some("app.conf".as_ref()));
+}
+
+#[cfg(all(feature = "fs", unix))]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_create_user_dir() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let test_dir = tempdir().unwrap();
+    unsafe {
+        env::set_var("XDG_RUNTIME_DIR", test_dir.path().join("runtime"));
+        env::set_var("XDG_DATA_HOME", test_dir.path().join("data"));
+    }
+    let mode = |p: &Path| fs::metadata(p).unwrap().permissions().mode() & 0o777;
+
+    let dir = xdg_create_user_dir(&dirs::RUNTIME, "app/sockets").unwrap();
+    assert_eq!(dir, test_dir.path().join("runtime/app/sockets"));
+    assert_eq!(mode(&dir), 0o700);
+    assert_eq!(mode(dir.parent().unwrap()), 0o700);
+
+    let dir = xdg_create_user_dir(&dirs::DATA, "app").unwrap();
+    assert!(dir.is_dir());
+}
+
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_suffix_leading_dot() {
+    unsafe {
+        env::set_var("XDG_CONFIG_HOME", "/config");
+        env::set_var("XDG_CONFIG_DIRS", "/sys");
+    }
+
+    let user = xdg_user_dir(&dirs::CONFIG, "./app").unwrap();
+    assert_eq!(user, PathBuf::from("/config/app"));
+    assert_eq!(user.to_str(), Some("/config/app"));
+    assert_eq!(
+        xdg_system_dirs(&dirs::CONFIG, "./app/config").unwrap()[0].to_str(),
+        Some("/sys/app/config")
+    );
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_write_user_file() {
+    let test_dir = tempdir().unwrap();
+    unsafe { env::set_var("XDG_STATE_HOME", test_dir.path()) };
+
+    let path = xdg_write_user_file(&dirs::STATE, "app/state", b"1").unwrap();
+    assert_eq!(path, test_dir.path().join("app/state"));
+    assert_eq!(fs::read(&path).unwrap(), b"1");
+
+    // Replacing a private file keeps it private
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        xdg_write_user_file(&dirs::STATE, "app/state", "2").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"2");
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+    }
+
+    // Renaming over a non-empty directory fails, and the temporary file is cleaned up
+    fs::create_dir_all(test_dir.path().join("app/dir/child")).unwrap();
+    assert!(matches!(
+        xdg_write_user_file(&dirs::STATE, "app/dir", b"2"),
+        Err(Error::Io(..))
+    ));
+    assert_eq!(
+        fs::read_dir(test_dir.path().join("app")).unwrap().count(),
+        2
+    );
+}
+
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_context_systemd_dirs() {
+    unsafe {
+        env::set_var("XDG_STATE_HOME", "/state");
+        env::set_var("XDG_CACHE_HOME", "/cache");
+        env::set_var("STATE_DIRECTORY", "/var/lib/service:/var/lib/other");
+        env::remove_var("CACHE_DIRECTORY");
+    }
+    let ctx = XdgContext::new().with_systemd_dirs(true);
+
+    assert_eq!(
+        ctx.user_dir(&dirs::STATE, "db"),
+        Ok(PathBuf::from("/var/lib/service/db"))
+    );
+    assert_eq!(
+        ctx.user_dir(&dirs::CACHE, "db"),
+        Ok(PathBuf::from("/cache/db"))
+    );
+    assert_eq!(
+        XdgContext::new().user_dir(&dirs::STATE, "db"),
+        Ok(PathBuf::from("/state/db"))
+    );
+
+    unsafe { env::remove_var("STATE_DIRECTORY") };
+}
+
+#[test]
+fn test_context_with_env() {
+    use std::collections::HashMap;
+
+    // No `#[serial]` needed since the process environment is not used
+    let env = HashMap::from([("HOME", "/home/user"), ("XDG_CONFIG_DIRS", "/a:/b")]);
+    assert_eq!(env.get("HOME"), Some(&"/home/user"));
+    assert_eq!(Env::get(&env, "HOME"), Some("/home/user".to_string()));
+
+    let ctx = XdgContext::new().with_env(env);
+    assert_eq!(
+        ctx.user_dir(&dirs::CONFIG, "app"),
+        Ok(PathBuf::from("/home/user/.config/app"))
+    );
+    assert_eq!(
+        ctx.system_dirs(&dirs::CONFIG, "app"),
+        Ok(vec![PathBuf::from("/a/app"), PathBuf::from("/b/app")])
+    );
+    assert_eq!(
+        ctx.location_of_with(&dirs::CONFIG, "app", |p| p.starts_with("/b")),
+        Ok(PathBuf::from("/b/app"))
+    );
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_enumeration_sorted() {
+    let test_dir = tempdir().unwrap();
+    let user = test_dir.path().join("user");
+    let system = test_dir.path().join("system");
+    // Created out of order, so that directory order is unlikely to match by chance
+    for file in ["app/c", "app/a/z", "app/b", "app/a/y"] {
+        let path = user.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        File::create(path).unwrap();
+    }
+    fs::create_dir_all(system.join("app")).unwrap();
+    File::create(system.join("app/a")).unwrap();
+    unsafe {
+        env::set_var("XDG_DATA_HOME", &user);
+        env::set_var("XDG_DATA_DIRS", &system);
+    }
+
+    let walk = || -> Vec<_> {
+        xdg_walk(&dirs::DATA, "app")
+            .map(|entry| entry.relative.to_string_lossy().to_string())
+            .collect()
+    };
+    assert_eq!(walk(), ["a/y", "a/z", "b", "c", "a"]);
+    assert_eq!(walk(), walk());
+    assert_eq!(xdg_installed_app_ids(&dirs::DATA, false), ["app"]);
+}
+
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_relative_paths_ignored() {
+    unsafe {
+        env::set_var("HOME", "/home/user");
+        env::set_var("XDG_CONFIG_HOME", "relative/config");
+        env::set_var("XDG_CONFIG_DIRS", "rel/path:/etc/xdg::./other:/opt/xdg");
+    }
+    assert_eq!(
+        xdg_user_dir(&dirs::CONFIG, "app"),
+        Ok(PathBuf::from("/home/user/.config/app"))
+    );
+    assert_eq!(
+        xdg_system_dirs(&dirs::CONFIG, "app"),
+        Ok(vec![
+            PathBuf::from("/etc/xdg/app"),
+            PathBuf::from("/opt/xdg/app")
+        ])
+    );
+
+    // Only relative entries: the same as not being set
+    unsafe { env::set_var("XDG_CONFIG_DIRS", "rel/path") };
+    assert_eq!(
+        xdg_system_dirs(&dirs::CONFIG, "app"),
+        Ok(vec![PathBuf::from("/etc/xdg/app")])
+    );
+}
+
+#[cfg(unix)]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_non_utf8_env_vars() {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    let user = OsStr::from_bytes(b"/data/\xff");
+    unsafe {
+        env::set_var("XDG_DATA_HOME", user);
+        env::set_var("XDG_DATA_DIRS", OsStr::from_bytes(b"/sys/\xfe:/usr/share"));
+    }
+
+    assert_eq!(
+        xdg_user_dir(&dirs::DATA, "app"),
+        Ok(Path::new(user).join("app"))
+    );
+    assert_eq!(
+        xdg_system_dirs(&dirs::DATA, "app"),
+        Ok(vec![
+            Path::new(OsStr::from_bytes(b"/sys/\xfe")).join("app"),
+            PathBuf::from("/usr/share/app"),
+        ])
+    );
+}
+
+#[cfg(unix)]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_path_suffix() {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    unsafe {
+        env::set_var("XDG_CONFIG_HOME", "/some/path");
+        env::set_var("XDG_CONFIG_DIRS", "/sys");
+    }
+
+    let suffix = Path::new(OsStr::from_bytes(b"app/\xff"));
+    assert_eq!(
+        xdg_config_dir(suffix),
+        Ok(Path::new("/some/path").join(suffix))
+    );
+    assert_eq!(
+        xdg_system_dirs(&dirs::CONFIG, suffix.as_os_str()),
+        Ok(vec![Path::new("/sys").join(suffix)])
+    );
+
+    let checked = ["/some/path", "/sys"]
+        .map(|dir| Path::new(dir).join(suffix).to_string_lossy().to_string())
+        .to_vec();
+    assert_eq!(
+        xdg_location_of_with(&dirs::CONFIG, suffix, |_| false),
+        Err(Error::NotFound(suffix.display().to_string(), checked))
+    );
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_locate_suffix_chain() {
+    let test_dir = tempdir().unwrap();
+    let user = test_dir.path().join("user");
+    let system = test_dir.path().join("system");
+    unsafe {
+        env::set_var("XDG_CONFIG_HOME", &user);
+        env::set_var("XDG_CONFIG_DIRS", &system);
+    }
+    let suffixes = ["app/local.toml", "app.toml"];
+
+    let checked = suffixes
+        .iter()
+        .flat_map(|s| [user.join(s), system.join(s)])
+        .map(|p| p.to_string_lossy().to_string())
+        .collect();
+    assert_eq!(
+        xdg_locate_suffix_chain(&dirs::CONFIG, &suffixes),
+        Err(Error::NotFound(
+            "app/local.toml, app.toml".to_string(),
+            checked
+        ))
+    );
+
+    // A system copy of an earlier suffix wins over a user copy of a later one
+    fs::create_dir_all(&user).unwrap();
+    File::create(user.join("app.toml")).unwrap();
+    fs::create_dir_all(system.join("app")).unwrap();
+    File::create(system.join("app/local.toml")).unwrap();
+    assert_eq!(
+        xdg_locate_suffix_chain(&dirs::CONFIG, &suffixes),
+        Ok(system.join("app/local.toml").canonicalize().unwrap())
+    );
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_create_dir_over_file() {
+    let test_dir = tempdir().unwrap();
+    unsafe { env::set_var("XDG_CONFIG_HOME", test_dir.path()) };
+    File::create(test_dir.path().join("app")).unwrap();
+
+    assert_eq!(
+        xdg_create_dir(&dirs::CONFIG, "app"),
+        Err(Error::NotADirectory(test_dir.path().join("app")))
+    );
+}
+
+#[test]
+fn test_error_clone() {
+    let errors = [
+        Error::EnvVarNotSet("XDG_RUNTIME_DIR"),
+        Error::NotFound("app".to_string(), vec!["/a/app".to_string()]),
+        Error::Io(PathBuf::from("/a"), std::io::ErrorKind::PermissionDenied),
+    ];
+    for error in errors {
+        assert_eq!(error.clone(), error);
+    }
+
+    assert_ne!(
+        Error::Io(PathBuf::from("/a"), std::io::ErrorKind::PermissionDenied),
+        Error::Io(PathBuf::from("/a"), std::io::ErrorKind::NotFound)
+    );
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_override_target() {
+    let test_dir = tempdir().unwrap();
+    let user = test_dir.path().join("user");
+    let system = test_dir.path().join("system");
+    unsafe {
+        env::set_var("XDG_CONFIG_HOME", &user);
+        env::set_var("XDG_CONFIG_DIRS", &system);
+    }
+    fs::create_dir_all(system.join("app")).unwrap();
+    fs::write(system.join("app/settings"), "a=1").unwrap();
+
+    let target = xdg_override_target(&dirs::CONFIG, "app/settings").unwrap();
+    assert_eq!(target, user.join("app/settings"));
+
+    fs::create_dir_all(user.join("app")).unwrap();
+    fs::write(&target, "a=2").unwrap();
+    assert_eq!(
+        xdg_all_locations_of(&dirs::CONFIG, "app/settings").unwrap(),
+        vec![
+            target.canonicalize().unwrap(),
+            system.join("app/settings").canonicalize().unwrap()
+        ]
+    );
+}
+
+#[cfg(all(feature = "fs", unix))]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_runtime_dir_or_default() {
+    unsafe { env::set_var("XDG_RUNTIME_DIR", "/run/user/1000") };
+    assert_eq!(
+        xdg_runtime_dir_or_default("app"),
+        Ok(PathBuf::from("/run/user/1000/app"))
+    );
+
+    // Whether the default is usable depends on the system running the test, but it must agree with the lenient lookup
+    unsafe { env::remove_var("XDG_RUNTIME_DIR") };
+    match xdg_runtime_dir_lenient("app").0 {
+        Some(path) => assert_eq!(xdg_runtime_dir_or_default("app"), Ok(path)),
+        None => assert_eq!(
+            xdg_runtime_dir_or_default("app"),
+            Err(Error::EnvVarNotSet("XDG_RUNTIME_DIR"))
+        ),
+    }
+    // The suffix stays under the default, without redundant separators
+    assert_eq!(
+        xdg_runtime_dir_or_default("/app/"),
+        xdg_runtime_dir_or_default("app")
+    );
+}
+
+#[cfg(all(feature = "fs", unix))]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_runtime_dir_checked() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let test_dir = tempdir().unwrap();
+    let runtime = test_dir.path().join("runtime");
+    fs::create_dir(&runtime).unwrap();
+    unsafe { env::set_var("XDG_RUNTIME_DIR", &runtime) };
+
+    fs::set_permissions(&runtime, fs::Permissions::from_mode(0o700)).unwrap();
+    assert_eq!(
+        xdg_runtime_dir_checked("app.sock"),
+        Ok(runtime.join("app.sock"))
+    );
+
+    fs::set_permissions(&runtime, fs::Permissions::from_mode(0o755)).unwrap();
+    assert_eq!(
+        xdg_runtime_dir_checked("app.sock"),
+        Err(Error::InsecureRuntimeDir(runtime.clone()))
+    );
+}
+
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_xdg_env_from_env() {
+    unsafe {
+        env::set_var("HOME", "/some/home");
+        env::set_var("XDG_DATA_HOME", "/data");
+        env::set_var("XDG_DATA_DIRS", "/sys1:/sys2");
+        env::remove_var("XDG_CONFIG_HOME");
+    }
+    let ctx = XdgContext::new().with_env(XdgEnv::from_env());
+
+    // Later changes to the process environment do not affect the snapshot
+    unsafe {
+        env::set_var("HOME", "/other/home");
+        env::set_var("XDG_DATA_HOME", "/other/data");
+        env::set_var("XDG_DATA_DIRS", "/other/sys");
+    }
+    assert_eq!(
+        ctx.user_dir(&dirs::DATA, "app"),
+        Ok(PathBuf::from("/data/app"))
+    );
+    assert_eq!(
+        ctx.user_dir(&dirs::CONFIG, "app"),
+        Ok(PathBuf::from("/some/home/.config/app"))
+    );
+    assert_eq!(
+        ctx.system_dirs(&dirs::DATA, "app"),
+        Ok(vec![PathBuf::from("/sys1/app"), PathBuf::from("/sys2/app")])
+    );
+
+    // Variables outside the XDG ones are captured too
+    unsafe { env::set_var("STATE_DIRECTORY", "/var/lib/service") };
+    let ctx = XdgContext::new()
+        .with_systemd_dirs(true)
+        .with_env(XdgEnv::from_env());
+    unsafe { env::remove_var("STATE_DIRECTORY") };
+    assert_eq!(
+        ctx.user_dir(&dirs::STATE, "db"),
+        Ok(PathBuf::from("/var/lib/service/db"))
+    );
+
+    // System dirs that are not valid unicode are kept as-is
+    #[cfg(unix)]
+    {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let dirs_var = OsStr::from_bytes(b"/sys\xff");
+        unsafe { env::set_var("XDG_DATA_DIRS", dirs_var) };
+        let snapshot = XdgEnv::from_env();
+        assert_eq!(snapshot.data_dirs.as_deref(), Some(dirs_var));
+        assert_eq!(
+            XdgContext::new()
+                .with_env(snapshot)
+                .system_dirs(&dirs::DATA, "app"),
+            xdg_system_dirs(&dirs::DATA, "app")
+        );
+    }
+}
+
+#[cfg(feature = "memmap2")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_mmap() {
+    let test_dir = tempdir().unwrap();
+    unsafe {
+        env::set_var("XDG_DATA_HOME", test_dir.path());
+        env::set_var("XDG_DATA_DIRS", test_dir.path().join("system"));
+    }
+
+    assert!(matches!(
+        xdg_mmap(&dirs::DATA, "app/db"),
+        Err(Error::NotFound(..))
+    ));
+
+    fs::create_dir_all(test_dir.path().join("app")).unwrap();
+    fs::write(test_dir.path().join("app/db"), "contents").unwrap();
+    let (path, map) = xdg_mmap(&dirs::DATA, "app/db").unwrap();
+    assert_eq!(path, test_dir.path().join("app/db").canonicalize().unwrap());
+    assert_eq!(&map[..], b"contents");
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_config_namespace() {
+    let test_dir = tempdir().unwrap();
+    unsafe { env::set_var("XDG_CONFIG_HOME", test_dir.path()) };
+    let config = config_namespace("app").unwrap();
+
+    // The base was resolved once, changing the environment does not affect it
+    unsafe { env::set_var("XDG_CONFIG_HOME", "/other") };
+    let app = test_dir.path().join("app");
+    assert_eq!(config.path(), app);
+    assert_eq!(config.file("settings.toml"), app.join("settings.toml"));
+
+    let themes = config.subdir("themes");
+    assert_eq!(themes.file("dark"), app.join("themes/dark"));
+    assert_eq!(themes.ensure().unwrap(), app.join("themes"));
+    assert!(app.join("themes").is_dir());
+
+    // Absolute names stay inside the namespace
+    assert_eq!(config.file("/x"), app.join("x"));
+    assert_eq!(config.subdir("/etc/").path(), app.join("etc"));
+}
+
+#[cfg(all(feature = "fs", unix))]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_location_of_no_follow() {
+    let test_dir = tempdir().unwrap();
+    let config = test_dir.path().join("config");
+    let dotfiles = test_dir.path().join("dotfiles/app");
+    fs::create_dir_all(&config).unwrap();
+    fs::create_dir_all(&dotfiles).unwrap();
+    File::create(dotfiles.join("settings")).unwrap();
+    std::os::unix::fs::symlink(&dotfiles, config.join("app")).unwrap();
+    unsafe {
+        env::set_var("XDG_CONFIG_HOME", &config);
+        env::set_var("XDG_CONFIG_DIRS", test_dir.path().join("system"));
+    }
+
+    assert_eq!(
+        xdg_location_of_no_follow(&dirs::CONFIG, "app/settings"),
+        Ok(config.join("app/settings"))
+    );
+    assert_eq!(
+        xdg_location_of(&dirs::CONFIG, "app/settings"),
+        Ok(dotfiles.join("settings").canonicalize().unwrap())
+    );
+}
+
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_suffix_trailing_separator() {
+    unsafe {
+        env::remove_var("XDG_CONFIG_HOME");
+        env::set_var("HOME", "/some/home");
+        env::set_var("XDG_CONFIG_DIRS", "/sys/");
+    }
+
+    for (suffix, expected) in [("app/", "app"), ("a/b/", "a/b"), ("a/b//", "a/b")] {
+        let user = xdg_user_dir(&dirs::CONFIG, suffix).unwrap();
+        assert_eq!(
+            user.as_os_str(),
+            Path::new("/some/home/.config").join(expected).as_os_str()
+        );
+        let system = xdg_system_dirs(&dirs::CONFIG, suffix).unwrap();
+        assert_eq!(
+            system[0].as_os_str(),
+            Path::new("/sys").join(expected).as_os_str()
+        );
+    }
+
+    // The base directory itself has no trailing separator
+    assert_eq!(
+        xdg_user_dir(&dirs::CONFIG, "").unwrap().as_os_str(),
+        "/some/home/.config"
+    );
+}
+
+/// Whether file permissions are enforced for the current user, which they are not for root (e.g. in CI containers).
+/// Prints that `test` is skipped if not.
+#[cfg(all(feature = "fs", unix))]
+fn permissions_enforced(test: &str) -> bool {
+    // Safety: geteuid has no preconditions and cannot fail
+    let root = unsafe { libc::geteuid() } == 0;
+    if root {
+        eprintln!("skipping {test}: permissions are not enforced when running as root");
+    }
+    !root
+}
+
+#[cfg(all(feature = "fs", unix))]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_location_of_permission_denied() {
+    use std::os::unix::fs::PermissionsExt;
+
+    if !permissions_enforced("test_location_of_permission_denied") {
+        return;
+    }
+
+    let test_dir = tempdir().unwrap();
+    let locked = test_dir.path().join("locked");
+    let system = test_dir.path().join("system");
+    fs::create_dir_all(locked.join("app")).unwrap();
+    fs::create_dir_all(&system).unwrap();
+    fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+    unsafe {
+        env::set_var("XDG_CONFIG_HOME", &locked);
+        env::set_var("XDG_CONFIG_DIRS", &system);
+    }
+
+    let res = xdg_location_of(&dirs::CONFIG, "app");
+    let kind_res = xdg_location_of_kind(&dirs::CONFIG, "app", EntryKind::Any);
+    let chain_denied = xdg_locate_suffix_chain(&dirs::CONFIG, &["app/local.toml", "app.toml"]);
+    // A denied candidate for the first suffix does not stop the chain from finding a later one
+    File::create(system.join("app.toml")).unwrap();
+    let chain_found = xdg_locate_suffix_chain(&dirs::CONFIG, &["app/local.toml", "app.toml"]);
+    fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+
+    assert_eq!(res, Err(Error::PermissionDenied(locked.join("app"))));
+    assert_eq!(kind_res, res);
+    assert_eq!(
+        chain_denied,
+        Err(Error::PermissionDenied(locked.join("app/local.toml")))
+    );
+    assert_eq!(
+        chain_found,
+        Ok(system.join("app.toml").canonicalize().unwrap())
+    );
+}
+
+#[cfg(all(feature = "toml", feature = "json", unix))]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_xdg_load_config_permission_denied() {
+    use std::os::unix::fs::PermissionsExt;
+
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct Config {
+        name: String,
+    }
+
+    if !permissions_enforced("test_xdg_load_config_permission_denied") {
+        return;
+    }
+
+    let test_dir = tempdir().unwrap();
+    let locked = test_dir.path().join("locked");
+    let system = test_dir.path().join("system");
+    fs::create_dir_all(&locked).unwrap();
+    fs::create_dir_all(&system).unwrap();
+    fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+    unsafe {
+        env::set_var("XDG_CONFIG_HOME", &locked);
+        env::set_var("XDG_CONFIG_DIRS", &system);
+    }
+
+    let denied = xdg_load_config::<Config>("app");
+    // A denied `.toml` candidate does not stop the `.json` one from being found
+    fs::write(system.join("app.json"), r#"{"name": "json"}"#).unwrap();
+    let found = xdg_load_config::<Config>("app");
+    fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+
+    assert_eq!(
+        denied,
+        Err(Error::PermissionDenied(locked.join("app.toml")))
+    );
+    assert_eq!(
+        found,
+        Ok(Config {
+            name: "json".to_string()
+        })
+    );
+}
+
+#[cfg(all(feature = "fs", unix))]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_candidate_status() {
+    let test_dir = tempdir().unwrap();
+    let dirs = ["user", "sys1", "sys2", "sys3"].map(|d| test_dir.path().join(d));
+    unsafe {
+        env::set_var("XDG_DATA_HOME", &dirs[0]);
+        env::set_var("XDG_DATA_DIRS", env::join_paths(&dirs[1..]).unwrap());
+    }
+    fs::create_dir_all(dirs[0].join("app")).unwrap();
+    fs::create_dir_all(&dirs[1]).unwrap();
+    File::create(dirs[1].join("app")).unwrap();
+    fs::create_dir_all(&dirs[2]).unwrap();
+    std::os::unix::fs::symlink(test_dir.path().join("missing"), dirs[2].join("app")).unwrap();
+
+    let status = |path: &PathBuf, flags: [bool; 4]| CandidateStatus {
+        path: path.join("app"),
+        exists: flags[0],
+        is_dir: flags[1],
+        is_file: flags[2],
+        is_symlink: flags[3],
+    };
+    assert_eq!(
+        xdg_candidate_status(&dirs::DATA, "app"),
+        vec![
+            status(&dirs[0], [true, true, false, false]),
+            status(&dirs[1], [true, false, true, false]),
+            status(&dirs[2], [true, false, false, true]),
+            status(&dirs[3], [false, false, false, false]),
+        ]
+    );
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_duplicate_system_dirs() {
+    let test_dir = tempdir().unwrap();
+    let share = test_dir.path().join("share");
+    let other = test_dir.path().join("other");
+    let dotdot = test_dir.path().join("local/../share");
+    fs::create_dir_all(test_dir.path().join("local")).unwrap();
+    fs::create_dir_all(&share).unwrap();
+    unsafe {
+        env::set_var("XDG_DATA_HOME", test_dir.path().join("user"));
+        env::set_var(
+            "XDG_DATA_DIRS",
+            env::join_paths([&share, &other, &share, &dotdot]).unwrap(),
+        );
+    }
+
+    // Exact duplicates are dropped, but other spellings of the same location are kept
+    assert_eq!(
+        xdg_system_dirs(&dirs::DATA, "app"),
+        Ok(vec![
+            share.join("app"),
+            other.join("app"),
+            dotdot.join("app")
+        ])
+    );
+
+    // Searching only checks each location once
+    let checked = [test_dir.path().join("user"), share.clone(), other.clone()]
+        .map(|dir| dir.join("app").to_string_lossy().to_string())
+        .to_vec();
+    assert_eq!(
+        xdg_location_of(&dirs::DATA, "app"),
+        Err(Error::NotFound("app".to_string(), checked))
+    );
+}
+
+#[cfg(not(all(feature = "cross-platform", any(windows, target_os = "macos"))))]
+#[test]
+fn test_xdg_defaults_without_cross_platform() {
+    for xdg_dir in dirs::ALL {
+        assert_eq!(xdg_dir.platform_var(), None);
+    }
+    assert_eq!(dirs::CONFIG.home_fallback(), Some(".config"));
+}
+
+#[cfg(all(feature = "cross-platform", windows))]
+#[test]
+fn test_cross_platform_windows() {
+    use std::collections::HashMap;
+
+    let env = HashMap::from([
+        ("USERPROFILE", r"C:\Users\me"),
+        ("APPDATA", r"C:\Users\me\AppData\Roaming"),
+        ("LOCALAPPDATA", r"C:\Users\me\AppData\Local"),
+        ("XDG_DATA_DIRS", r"C:\a;C:\b"),
+    ]);
+    let ctx = XdgContext::new().with_env(env);
+
+    assert_eq!(
+        ctx.user_dir(&dirs::CONFIG, "app"),
+        Ok(PathBuf::from(r"C:\Users\me\AppData\Roaming\app"))
+    );
+    assert_eq!(
+        ctx.user_dir(&dirs::DATA, "app"),
+        Ok(PathBuf::from(r"C:\Users\me\AppData\Local\app"))
+    );
+    assert_eq!(
+        ctx.system_dirs(&dirs::DATA, "app"),
+        Ok(vec![PathBuf::from(r"C:\a\app"), PathBuf::from(r"C:\b\app")])
+    );
+}
+
+#[cfg(all(feature = "cross-platform", target_os = "macos"))]
+#[test]
+fn test_cross_platform_macos() {
+    use std::collections::HashMap;
+
+    let ctx = XdgContext::new().with_env(HashMap::from([("HOME", "/Users/me")]));
+    assert_eq!(
+        ctx.user_dir(&dirs::CONFIG, "app"),
+        Ok(PathBuf::from("/Users/me/Library/Application Support/app"))
+    );
+    assert_eq!(
+        ctx.user_dir(&dirs::CACHE, "app"),
+        Ok(PathBuf::from("/Users/me/Library/Caches/app"))
+    );
+}
+
+#[cfg(feature = "toml")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_xdg_load_layered() {
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct Config {
+        name: Option<String>,
+        size: Option<u32>,
+    }
+
+    impl Merge for Config {
+        fn merge(&mut self, higher: Self) {
+            self.name = higher.name.or(self.name.take());
+            self.size = higher.size.or(self.size);
+        }
+    }
+
+    let test_dir = tempdir().unwrap();
+    let dirs = ["user", "sys1", "sys2"].map(|d| test_dir.path().join(d).join("app"));
+    unsafe {
+        env::set_var("XDG_CONFIG_HOME", test_dir.path().join("user"));
+        env::set_var(
+            "XDG_CONFIG_DIRS",
+            env::join_paths([test_dir.path().join("sys1"), test_dir.path().join("sys2")]).unwrap(),
+        );
+    }
+    for dir in &dirs {
+        fs::create_dir_all(dir).unwrap();
+    }
+
+    assert!(matches!(
+        xdg_load_layered::<Config>("app/config.toml"),
+        Err(Error::NotFound(..))
+    ));
+
+    fs::write(dirs[2].join("config.toml"), "name = \"sys2\"\nsize = 2").unwrap();
+    fs::write(dirs[1].join("config.toml"), "name = \"sys1\"").unwrap();
+    assert_eq!(
+        xdg_load_layered("app/config.toml"),
+        Ok(Config {
+            name: Some("sys1".to_string()),
+            size: Some(2),
+        })
+    );
+    // Only the first format found in a directory is a layer, a stray file in another format is ignored
+    #[cfg(feature = "json")]
+    {
+        fs::write(dirs[1].join("config.json"), r#"{"name": "stray"}"#).unwrap();
+        assert_eq!(
+            xdg_load_layered("app/config"),
+            Ok(Config {
+                name: Some("sys1".to_string()),
+                size: Some(2),
+            })
+        );
+    }
+    // A leading separator does not make every layer read the same absolute file
+    assert_eq!(
+        xdg_load_layered::<Config>("/app/config.toml"),
+        xdg_load_layered("app/config.toml")
+    );
+
+    fs::write(dirs[0].join("config.toml"), "size = ").unwrap();
+    assert_eq!(
+        xdg_load_layered::<Config>("app/config.toml").map_err(|e| match e {
+            Error::Parse(failures) => failures.into_iter().map(|(p, _)| p).collect(),
+            _ => vec![],
+        }),
+        Err(vec![dirs[0].join("config.toml")])
+    );
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_user_dirs() {
+    let test_dir = tempdir().unwrap();
+    unsafe {
+        env::set_var("HOME", "/some/home");
+        env::set_var("XDG_CONFIG_HOME", test_dir.path());
+        env::set_var("XDG_MUSIC_DIR", "/env/music");
+        env::set_var("XDG_VIDEOS_DIR", "/env/videos");
+        env::remove_var("XDG_TEMPLATES_DIR");
+    }
+    fs::write(
+        test_dir.path().join("user-dirs.dirs"),
+        r#"# Written by xdg-user-dirs-update
+XDG_DESKTOP_DIR="$HOME/Desktop"
+XDG_DOWNLOAD_DIR="$HOME/Old"
+XDG_DOWNLOAD_DIR="$HOME/Downloads"
+XDG_DOCUMENTS_DIR="/mnt/docs"
+XDG_PUBLICSHARE_DIR="$HOME"
+XDG_VIDEOS_DIR="Videos"
+XDG_TEMPLATES_DIR="$HOMEWORK"
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        user_dirs::desktop_dir(),
+        Ok(PathBuf::from("/some/home/Desktop"))
+    );
+    assert_eq!(
+        user_dirs::download_dir(),
+        Ok(PathBuf::from("/some/home/Downloads"))
+    );
+    assert_eq!(user_dirs::documents_dir(), Ok(PathBuf::from("/mnt/docs")));
+    assert_eq!(
+        user_dirs::publicshare_dir(),
+        Ok(PathBuf::from("/some/home"))
+    );
+
+    // Not in the file, or invalid there: fall back to the environment
+    assert_eq!(user_dirs::music_dir(), Ok(PathBuf::from("/env/music")));
+    assert_eq!(user_dirs::videos_dir(), Ok(PathBuf::from("/env/videos")));
+    assert_eq!(
+        user_dirs::templates_dir(),
+        Err(Error::EnvVarNotSet("XDG_TEMPLATES_DIR"))
+    );
+}
+
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_xdg_candidates() {
+    unsafe {
+        env::set_var("XDG_DATA_HOME", "/data");
+        env::set_var("XDG_DATA_DIRS", "/sys1:/sys2");
+        env::set_var("XDG_CACHE_HOME", "/cache");
+        env::remove_var("XDG_RUNTIME_DIR");
+    }
+
+    assert_eq!(
+        xdg_candidates(&dirs::DATA, "app"),
+        Ok(Candidates {
+            user: Some(PathBuf::from("/data/app")),
+            system: vec![PathBuf::from("/sys1/app"), PathBuf::from("/sys2/app")],
+        })
+    );
+    assert_eq!(
+        xdg_candidates(&dirs::CACHE, "app"),
+        Ok(Candidates {
+            user: Some(PathBuf::from("/cache/app")),
+            system: vec![],
+        })
+    );
+    assert_eq!(
+        xdg_candidates(&dirs::RUNTIME, "app"),
+        Err(Error::EnvVarNotSet("XDG_RUNTIME_DIR"))
+    );
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_writable_dir() {
+    let test_dir = tempdir().unwrap();
+    let user = test_dir.path().join("user");
+    let system = test_dir.path().join("system");
+    unsafe {
+        env::set_var("XDG_DATA_HOME", &user);
+        env::set_var("XDG_DATA_DIRS", &system);
+    }
+
+    // The user directory is created, and no probe file is left behind
+    assert_eq!(
+        xdg_writable_dir(&dirs::DATA, "app/db"),
+        Ok(user.join("app/db"))
+    );
+    assert!(user.join("app/db").is_dir());
+    assert_eq!(fs::read_dir(user.join("app/db")).unwrap().count(), 0);
+
+    // A file in the way of the user directory falls through to an existing system directory
+    File::create(user.join("app/other")).unwrap();
+    fs::create_dir_all(system.join("app/other")).unwrap();
+    assert_eq!(
+        xdg_writable_dir(&dirs::DATA, "app/other"),
+        Ok(system.join("app/other"))
+    );
+
+    // System directories are not created
+    File::create(user.join("app/missing")).unwrap();
+    let checked = [&user, &system]
+        .map(|dir| dir.join("app/missing").to_string_lossy().to_string())
+        .to_vec();
+    assert_eq!(
+        xdg_writable_dir(&dirs::DATA, "app/missing"),
+        Err(Error::NotFound("app/missing".to_string(), checked))
+    );
+    assert!(!system.join("app/missing").exists());
+
+    // Concurrent probes of the same directory do not collide
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..8)
+            .map(|_| scope.spawn(|| xdg_writable_dir(&dirs::DATA, "app/db")))
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), Ok(user.join("app/db")));
+        }
+    });
+}
+
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_xdg_iter() {
+    unsafe {
+        env::set_var("XDG_DATA_HOME", "/data");
+        env::set_var("XDG_DATA_DIRS", "/sys1:/data:/sys2");
+        env::remove_var("XDG_RUNTIME_DIR");
+        env::set_var("XDG_STATE_HOME", "/state");
+    }
+
+    assert_eq!(
+        xdg_iter(&dirs::DATA, "app").collect::<Vec<_>>(),
+        xdg_candidate_paths(&dirs::DATA, "app")
+    );
+    assert_eq!(
+        xdg_iter(&dirs::DATA, "app").collect::<Vec<_>>(),
+        [
+            PathBuf::from("/data/app"),
+            PathBuf::from("/sys1/app"),
+            PathBuf::from("/sys2/app")
+        ]
+    );
+    assert_eq!(
+        xdg_iter(&dirs::DATA, "app").nth(1),
+        Some(PathBuf::from("/sys1/app"))
+    );
+
+    assert_eq!(
+        xdg_iter(&dirs::STATE, "app").collect::<Vec<_>>(),
+        [PathBuf::from("/state/app")]
+    );
+    assert_eq!(xdg_iter(&dirs::RUNTIME, "app").next(), None);
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_merge_config() {
+    let test_dir = tempdir().unwrap();
+    let dirs = ["user", "sys1", "sys2"].map(|d| test_dir.path().join(d));
+    unsafe {
+        env::set_var("XDG_CONFIG_HOME", &dirs[0]);
+        env::set_var("XDG_CONFIG_DIRS", env::join_paths(&dirs[1..]).unwrap());
+    }
+    let read_all = || {
+        xdg_merge_config("app.conf", String::new(), |acc, path| {
+            acc + &fs::read_to_string(path).unwrap()
+        })
+    };
+
+    assert_eq!(read_all(), Ok(String::new()));
+
+    for (dir, contents) in dirs.iter().zip(["user", "sys1", "sys2"]) {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join("app.conf"), contents).unwrap();
+    }
+    assert_eq!(read_all(), Ok("sys2sys1user".to_string()));
+}
+
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_no_redundant_separators() {
+    unsafe {
+        env::remove_var("XDG_DATA_HOME");
+        env::remove_var("XDG_DATA_DIRS");
+        env::set_var("HOME", "/some/home/");
+    }
+
+    for (suffix, expected) in [
+        ("foo/", "/some/home/.local/share/foo"),
+        ("/foo", "/some/home/.local/share/foo"),
+        ("//foo//bar", "/some/home/.local/share/foo/bar"),
+        ("", "/some/home/.local/share"),
+    ] {
+        assert_eq!(
+            xdg_user_dir(&dirs::DATA, suffix).unwrap().as_os_str(),
+            expected
+        );
+    }
+
+    assert_eq!(
+        xdg_system_dirs(&dirs::DATA, "/foo/").unwrap(),
+        [
+            PathBuf::from("/usr/local/share/foo"),
+            PathBuf::from("/usr/share/foo")
+        ]
+    );
+    let system = xdg_system_dirs(&dirs::DATA, "").unwrap();
+    assert_eq!(system[1].as_os_str(), "/usr/share");
+
+    for suffix in ["/app/", "//app", "./app"] {
+        assert_eq!(dirs::CONFIG.schema(suffix), dirs::CONFIG.schema("app"));
+    }
+    let schema = dirs::CONFIG.schema("/");
+    assert_eq!(schema.user_template, "$XDG_CONFIG_HOME");
+    assert_eq!(schema.system_defaults, ["/etc/xdg"]);
+}
+
+#[cfg(feature = "fs")]
+#[test]
+// Safety: serial because env var access must be single-threaded (even with different vars)
+#[serial]
+fn test_empty_suffix() {
+    let test_dir = tempdir().unwrap();
+    let user = test_dir.path().join("user");
+    let system = test_dir.path().join("system");
+    fs::create_dir(&system).unwrap();
+    unsafe {
+        env::set_var("XDG_CONFIG_HOME", format!("{}/", user.display()));
+        env::set_var("XDG_CONFIG_DIRS", format!("{}/", system.display()));
+    }
+
+    // The base directory itself, without the trailing separator from the environment
+    assert_eq!(
+        xdg_user_dir(&dirs::CONFIG, "").unwrap().as_os_str(),
+        user.as_os_str()
+    );
+    assert_eq!(
+        xdg_system_dirs(&dirs::CONFIG, "").unwrap().as_slice(),
+        std::slice::from_ref(&system)
+    );
+    assert_eq!(xdg_location_of(&dirs::CONFIG, ""), Ok(system.clone()));
+
+    fs::create_dir(&user).unwrap();
+    assert_eq!(xdg_location_of(&dirs::CONFIG, ""), Ok(user.clone()));
+
+    fs::remove_dir(&user).unwrap();
+    fs::remove_dir(&system).unwrap();
+    assert_eq!(
+        xdg_location_of(&dirs::CONFIG, ""),
+        Err(Error::NotFound(
+            String::new(),
+            vec![
+                user.to_string_lossy().to_string(),
+                system.to_string_lossy().to_string(),
+            ]
+        ))
+    );
+}
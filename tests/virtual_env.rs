@@ -0,0 +1,55 @@
+//! The virtual environment is global to the process and can only be registered once, so it is tested in its own binary.
+#![cfg(feature = "virtual-env")]
+
+use std::{collections::HashMap, env, path::PathBuf};
+
+use xdg_dirs::*;
+
+#[test]
+fn test_virtual_env() {
+    // Safety: this is the only test in this binary, so nothing else accesses the environment concurrently
+    unsafe {
+        env::set_var("XDG_DATA_HOME", "/process/data");
+        env::set_var("MYAPP_PROFILE", "process");
+        env::set_var("DBUS_SESSION_BUS_ADDRESS", "unix:path=/process/bus");
+    }
+
+    let vars = HashMap::from([
+        ("HOME".to_string(), "/virtual/home".to_string()),
+        ("XDG_DATA_DIRS".to_string(), "/virtual/share".to_string()),
+        ("MYAPP_PROFILE".to_string(), "virtual".to_string()),
+        (
+            "DBUS_SESSION_BUS_ADDRESS".to_string(),
+            "unix:path=/virtual/bus".to_string(),
+        ),
+    ]);
+    assert_eq!(set_virtual_env(vars.clone()), Ok(()));
+    assert_eq!(set_virtual_env(vars.clone()), Err(vars));
+
+    // Only the virtual environment is read, even for variables it does not set
+    assert_eq!(
+        xdg_user_dir(&dirs::DATA, "app"),
+        Ok(PathBuf::from("/virtual/home/.local/share/app"))
+    );
+    assert_eq!(
+        xdg_system_dirs(&dirs::DATA, "app"),
+        Ok(vec![PathBuf::from("/virtual/share/app")])
+    );
+    assert_eq!(
+        XdgEnv::from_env().home,
+        Some(PathBuf::from("/virtual/home"))
+    );
+
+    // Including the variables read outside of XDG path resolution
+    assert_eq!(
+        xdg_profiled_config("MYAPP_PROFILE", "myapp", "config.toml"),
+        Ok(PathBuf::from(
+            "/virtual/home/.config/myapp/virtual/config.toml"
+        ))
+    );
+    #[cfg(feature = "fs")]
+    assert_eq!(
+        dbus_session_address(),
+        Some("unix:path=/virtual/bus".to_string())
+    );
+}